@@ -0,0 +1,95 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use nn_multi_class_classification_lib::nn::{
+    activation_functions::ActivationFunction, float::Float, NeuralNetwork,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+// two noisy blobs per class on a 2d plane, the same shape of data the UI generates
+fn dataset(samples: usize, classes: usize, seed: u64) -> Vec<(Vec<f64>, Vec<f64>)> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..samples)
+        .map(|i| {
+            let class = i % classes;
+            let center = class as f64 * 10.;
+            let inputs = vec![
+                center + rng.gen_range(-4.0..4.0),
+                center + rng.gen_range(-4.0..4.0),
+            ];
+            let mut y_desired = vec![0.; classes];
+            y_desired[class] = 1.;
+            (inputs, y_desired)
+        })
+        .collect()
+}
+
+// seeded so every run measures the same data and starting weights
+fn network<T: Float>(hidden_layers: Vec<usize>, batch_size: usize) -> NeuralNetwork<T> {
+    let activation_functions = vec![ActivationFunction::Relu; hidden_layers.len()];
+    let mut nn = NeuralNetwork::with_seed(
+        Some(42),
+        2,
+        hidden_layers,
+        &activation_functions,
+        3,
+        ActivationFunction::Softmax,
+        0.01,
+        dataset(1000, 3, 1),
+        dataset(200, 3, 2),
+        vec![],
+    );
+    nn.set_batch_size(batch_size);
+    nn
+}
+
+fn epoch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("epoch");
+    for topology in [vec![8, 4], vec![64, 64], vec![256, 128]] {
+        for batch_size in [1, 32] {
+            let mut nn = network::<f64>(topology.clone(), batch_size);
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", topology), batch_size),
+                &batch_size,
+                |b, _| b.iter(|| nn.epoch()),
+            );
+        }
+    }
+    group.finish();
+}
+
+// same training in single precision
+fn epoch_f32(c: &mut Criterion) {
+    let mut group = c.benchmark_group("epoch f32");
+    for batch_size in [1, 32] {
+        let mut nn = network::<f32>(vec![256, 128], batch_size);
+        group.bench_with_input(
+            BenchmarkId::new("[256, 128]", batch_size),
+            &batch_size,
+            |b, _| b.iter(|| nn.epoch()),
+        );
+    }
+    group.finish();
+}
+
+fn predict(c: &mut Criterion) {
+    let nn = network::<f64>(vec![64, 64], 1);
+    c.bench_function("predict [64, 64]", |b| {
+        b.iter(|| nn.predict(&vec![3.0, 7.0]))
+    });
+
+    let mut scratch = nn.scratch();
+    let mut outputs = vec![0.0; nn.outputs_count()];
+    c.bench_function("predict_into [64, 64]", |b| {
+        b.iter(|| nn.predict_into(&[3.0, 7.0], &mut scratch, &mut outputs))
+    });
+
+    // the 50×50 heatmap grid
+    let grid = (0..2500)
+        .map(|i| [(i % 50) as f64 * 2.0, (i / 50) as f64 * 2.0])
+        .collect::<Vec<_>>();
+    c.bench_function("predict_many [64, 64] 2500 samples", |b| {
+        b.iter(|| nn.predict_many(&grid))
+    });
+}
+
+criterion_group!(benches, epoch, epoch_f32, predict);
+criterion_main!(benches);
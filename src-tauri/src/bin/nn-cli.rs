@@ -0,0 +1,11 @@
+// Trains a network from a settings file and explains it without the UI, see cli::run for the commands.
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match nn_multi_class_classification_lib::cli::run(&args) {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::atomic::AtomicBool;
+
+use crate::{
+    build_network,
+    networks::NamedNetwork,
+    nn::Split,
+    search::{search, train, SearchSettings},
+    Settings,
+};
+
+const USAGE: &str = "Usage:
+  nn-cli saliency <settings.json> <x> <y>
+  nn-cli importance <settings.json> [repeats]
+  nn-cli search <settings.json> <search.json>
+
+The settings file holds the JSON the app sends to reset, data included.
+The network is trained for maxEpochs epochs (or until desiredMse) before explaining it.
+The search file holds the search settings the app sends to search_hyperparameters.";
+
+/**
+ * Runs one command of the nn-cli binary, returns the JSON to print.
+ */
+pub fn run(args: &[String]) -> Result<String, String> {
+    let (command, args) = args.split_first().ok_or(USAGE)?;
+    let json = match (command.as_str(), args) {
+        ("saliency", [settings, x, y]) => {
+            let inputs = [x, y]
+                .iter()
+                .map(|v| {
+                    v.parse::<f64>()
+                        .map_err(|_| format!("Invalid input: {}", v))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let network = trained(&read_settings(settings)?)?;
+            serde_json::to_string_pretty(&network.nn.saliency(&inputs)?)
+        }
+        ("importance", [settings, repeats @ ..]) if repeats.len() <= 1 => {
+            let repeats = match repeats.first() {
+                Some(repeats) => repeats
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid repeats: {}", repeats))?,
+                None => 5,
+            };
+            let network = trained(&read_settings(settings)?)?;
+            serde_json::to_string_pretty(
+                &network.nn.permutation_importance(Split::Testing, repeats)?,
+            )
+        }
+        ("search", [settings, search_settings]) => {
+            let json = std::fs::read_to_string(search_settings)
+                .map_err(|e| format!("Cannot read {}: {}", search_settings, e))?;
+            let search_settings: SearchSettings = serde_json::from_str(&json)
+                .map_err(|e| format!("Cannot read {}: {}", search_settings, e))?;
+            let result = search(
+                &read_settings(settings)?,
+                &search_settings,
+                &AtomicBool::new(false),
+            )?;
+            serde_json::to_string_pretty(&result)
+        }
+        _ => return Err(USAGE.to_string()),
+    };
+    json.map_err(|e| e.to_string())
+}
+
+fn read_settings(path: &str) -> Result<Settings, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Cannot read {}: {}", path, e))
+}
+
+// the network of the settings trained like learn would
+fn trained(settings: &Settings) -> Result<NamedNetwork, String> {
+    let mut network = NamedNetwork::new(build_network(settings)?, Some(settings.clone()));
+    train(&mut network, settings.max_epochs, &AtomicBool::new(false))?;
+    Ok(network)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        nn::explain::Saliency,
+        search::{tests::settings, SearchResult},
+    };
+
+    #[test]
+    fn saliency_of_a_trained_network() {
+        let path = std::env::temp_dir().join(format!("nn-cli-{}.json", std::process::id()));
+        std::fs::write(&path, serde_json::to_string(&settings()).unwrap()).unwrap();
+        let path = path.to_str().unwrap().to_string();
+
+        let args = ["saliency", &path, "3", "10"].map(String::from);
+        let saliency: Saliency = serde_json::from_str(&run(&args).unwrap()).unwrap();
+        assert_eq!(saliency.gradients.len(), 2);
+        assert!(saliency.gradients.iter().all(|g| g.len() == 2));
+
+        assert!(run(&["saliency", &path].map(String::from)).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn search_prints_the_ranked_trials() {
+        let dir = std::env::temp_dir();
+        let settings_path = dir.join(format!(
+            "nn-cli-search-settings-{}.json",
+            std::process::id()
+        ));
+        let search_path = dir.join(format!("nn-cli-search-{}.json", std::process::id()));
+        std::fs::write(&settings_path, serde_json::to_string(&settings()).unwrap()).unwrap();
+        std::fs::write(
+            &search_path,
+            r#"{
+                "parameters": [{ "path": "alpha", "space": { "type": "choice", "values": [0.05, 0.1] } }],
+                "strategy": { "type": "grid" },
+                "maxEpochs": 2
+            }"#,
+        )
+        .unwrap();
+
+        let args = [
+            "search",
+            settings_path.to_str().unwrap(),
+            search_path.to_str().unwrap(),
+        ]
+        .map(String::from);
+        let result: SearchResult = serde_json::from_str(&run(&args).unwrap()).unwrap();
+        assert_eq!(result.trials.len(), 2);
+        assert_eq!(result.best.unwrap().index, result.trials[0].index);
+
+        std::fs::remove_file(settings_path).unwrap();
+        std::fs::remove_file(search_path).unwrap();
+    }
+}
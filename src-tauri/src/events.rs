@@ -0,0 +1,159 @@
+use std::{collections::BTreeMap, time::Instant};
+
+use serde::Serialize;
+
+use crate::{
+    heatmap::{HeatmapBounds, HeatmapSurfaces, NeuronHeatmaps},
+    nn::{metrics::Metric, stats::NetworkStats},
+    ClientState,
+};
+
+// client state updates per second while learning when the settings do not set one
+pub const DEFAULT_FRAME_RATE: f64 = 30.0;
+
+/**
+ * What changed in the client state since the previous update.
+ *
+ * histories only carry the new points, the frontend appends them. the heatmap is only
+ * sent again after it was recomputed.
+ */
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientStateDelta {
+    is_learning: bool,
+    epoch: Option<usize>,
+    mse: Option<f64>,
+    mse_validation: Option<f64>,
+    mse_history: Vec<f64>,
+    mse_validation_history: Vec<f64>,
+    metric_history: BTreeMap<Metric, Vec<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    layer_stats: Option<NetworkStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heatmap: Option<Vec<Vec<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heatmap_range: Option<(f64, f64)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heatmap_bounds: Option<HeatmapBounds>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heatmap_resolution: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    heatmap_surfaces: Option<HeatmapSurfaces>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    neuron_heatmaps: Option<NeuronHeatmaps>,
+}
+
+/**
+ * What the frontend already received, so the next update only carries the difference.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct SentState {
+    mse_history: usize,
+    mse_validation_history: usize,
+    metric_history: BTreeMap<Metric, usize>,
+    heatmap_version: u64,
+    at: Option<Instant>,
+}
+
+impl SentState {
+    // after the whole client state was sent
+    pub fn synced(client_state: &ClientState) -> Self {
+        SentState {
+            mse_history: client_state.mse_history.as_ref().map_or(0, |h| h.len()),
+            mse_validation_history: client_state
+                .mse_validation_history
+                .as_ref()
+                .map_or(0, |h| h.len()),
+            metric_history: client_state
+                .metric_history
+                .iter()
+                .flatten()
+                .map(|(&metric, values)| (metric, values.len()))
+                .collect(),
+            heatmap_version: client_state.heatmap_version,
+            at: Some(Instant::now()),
+        }
+    }
+
+    // whether the previous update is at least one frame old, frame_rate <= 0 uses the default
+    pub fn is_due(&self, frame_rate: f64) -> bool {
+        let frame_rate = if frame_rate > 0.0 {
+            frame_rate
+        } else {
+            DEFAULT_FRAME_RATE
+        };
+        self.at
+            .is_none_or(|at| at.elapsed().as_secs_f64() >= 1.0 / frame_rate)
+    }
+
+    /**
+     * The changes since the previous update, marks them as sent.
+     */
+    pub fn delta(&mut self, client_state: &ClientState) -> ClientStateDelta {
+        let heatmap_changed = client_state.heatmap_version != self.heatmap_version;
+        let delta = ClientStateDelta {
+            is_learning: client_state.is_learning,
+            epoch: client_state.epoch,
+            mse: client_state.mse,
+            mse_validation: client_state.mse_validation,
+            mse_history: tail(&client_state.mse_history, self.mse_history),
+            mse_validation_history: tail(
+                &client_state.mse_validation_history,
+                self.mse_validation_history,
+            ),
+            metric_history: client_state
+                .metric_history
+                .iter()
+                .flatten()
+                .map(|(metric, values)| {
+                    let sent = self.metric_history.get(metric).copied().unwrap_or(0);
+                    (*metric, values[sent.min(values.len())..].to_vec())
+                })
+                .filter(|(_, values)| !values.is_empty())
+                .collect(),
+            layer_stats: client_state.layer_stats.clone(),
+            heatmap: if_changed(&client_state.heatmap, heatmap_changed),
+            heatmap_range: if_changed(&client_state.heatmap_range, heatmap_changed),
+            heatmap_bounds: if_changed(&client_state.heatmap_bounds, heatmap_changed),
+            heatmap_resolution: if_changed(&client_state.heatmap_resolution, heatmap_changed),
+            heatmap_surfaces: if_changed(&client_state.heatmap_surfaces, heatmap_changed),
+            neuron_heatmaps: if_changed(&client_state.neuron_heatmaps, heatmap_changed),
+        };
+        *self = SentState::synced(client_state);
+        delta
+    }
+}
+
+// the values after the first sent ones
+fn tail(history: &Option<Vec<f64>>, sent: usize) -> Vec<f64> {
+    history
+        .as_ref()
+        .map_or(vec![], |h| h[sent.min(h.len())..].to_vec())
+}
+
+fn if_changed<V: Clone>(value: &Option<V>, changed: bool) -> Option<V> {
+    value.clone().filter(|_| changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_STATS;
+
+    #[test]
+    fn deltas_only_carry_what_changed() {
+        let mut client_state = DEFAULT_STATS.clone();
+        client_state.mse_history = Some(vec![0.5, 0.4]);
+        let mut sent = SentState::synced(&client_state);
+
+        client_state.mse_history.as_mut().unwrap().push(0.3);
+        let delta = sent.delta(&client_state);
+        assert_eq!(delta.mse_history, vec![0.3]);
+        assert!(delta.heatmap.is_none());
+
+        client_state.heatmap_version += 1;
+        let delta = sent.delta(&client_state);
+        assert!(delta.mse_history.is_empty());
+        assert!(delta.heatmap.is_some());
+    }
+}
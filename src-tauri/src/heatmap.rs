@@ -0,0 +1,327 @@
+use serde::{Deserialize, Serialize};
+
+use crate::nn::{any::AnyNetwork, TaskType};
+
+// input space of the drawing area, used when the network has no data to fit the bounds to
+const DEFAULT_BOUNDS: HeatmapBounds = HeatmapBounds {
+    x: (0.0, 100.0),
+    y: (0.0, 100.0),
+};
+
+// cells per axis, more than a million predictions per heatmap is never useful
+const MAX_RESOLUTION: usize = 1000;
+
+/**
+ * (min, max) of the two inputs covered by the heatmap.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapBounds {
+    pub x: (f64, f64),
+    pub y: (f64, f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HeatmapSettings {
+    // cells along each axis
+    pub resolution: usize,
+    // fixed bounds, fitted to the data extent when missing
+    pub bounds: Option<HeatmapBounds>,
+    // fraction of the data extent added on every side of fitted bounds
+    pub margin: f64,
+    // epochs between two heatmap updates while learning (at most one per frame),
+    // 0 only updates when learning ends
+    pub update_interval: usize,
+    // cells per axis of the hidden neuron maps updated with the heatmap, 0 disables them
+    pub neuron_resolution: usize,
+}
+
+impl Default for HeatmapSettings {
+    fn default() -> Self {
+        HeatmapSettings {
+            resolution: 50,
+            bounds: None,
+            margin: 0.05,
+            update_interval: 1,
+            neuron_resolution: 20,
+        }
+    }
+}
+
+/**
+ * Predictions of the network over a resolution × resolution grid.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Heatmap {
+    pub resolution: usize,
+    pub bounds: HeatmapBounds,
+    // outputs of every cell center, row by row from (x.0, y.0)
+    pub values: Vec<Vec<f64>>,
+    // (min, max) predicted value when a regression surface is scaled to 0..1
+    pub range: Option<(f64, f64)>,
+    // uncertainty of the prediction in every cell, classification tasks only
+    pub surfaces: Option<HeatmapSurfaces>,
+}
+
+/**
+ * Outputs y of every hidden neuron over a grid covering the same bounds as the heatmap.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NeuronHeatmaps {
+    pub resolution: usize,
+    pub bounds: HeatmapBounds,
+    // [hidden layer][neuron][cell], raw outputs, not scaled to 0..1
+    pub layers: Vec<Vec<Vec<f64>>>,
+}
+
+/**
+ * Surfaces derived from the class probabilities of every heatmap cell.
+ *
+ * all values are in 0..1 so they render like the probabilities, multi-label tasks treat
+ * every label as its own yes / no decision and average over the labels.
+ */
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapSurfaces {
+    // most probable class
+    pub predicted_class: Vec<usize>,
+    // probability of the predicted class
+    pub confidence: Vec<f64>,
+    // predictive entropy divided by its maximum, 1 is a uniform guess
+    pub entropy: Vec<f64>,
+    // probability difference between the two most probable classes
+    pub margin: Vec<f64>,
+}
+
+impl HeatmapSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(2..=MAX_RESOLUTION).contains(&self.resolution) {
+            return Err(format!(
+                "Heatmap resolution must be between 2 and {}",
+                MAX_RESOLUTION
+            ));
+        }
+        if self.neuron_resolution != 0 && !(2..=MAX_RESOLUTION).contains(&self.neuron_resolution) {
+            return Err(format!(
+                "Neuron heatmap resolution must be 0 or between 2 and {}",
+                MAX_RESOLUTION
+            ));
+        }
+        if let Some(HeatmapBounds { x, y }) = self.bounds {
+            if !(x.0 < x.1 && y.0 < y.1) {
+                return Err("Heatmap bounds must have min < max".to_string());
+            }
+        }
+        if self.margin.is_nan() || self.margin < 0.0 {
+            return Err("Heatmap margin cannot be negative".to_string());
+        }
+        Ok(())
+    }
+
+    /**
+     * The fixed bounds, or the extent of the network data (its normalization factors) plus the margin.
+     */
+    pub fn bounds(&self, nn: &AnyNetwork) -> HeatmapBounds {
+        if let Some(bounds) = self.bounds {
+            return bounds;
+        }
+        let fit = |i: usize, default: (f64, f64)| match nn.normalization_factors().get(i) {
+            Some(&(min, max)) if min <= max => {
+                // a constant input still gets an area around it
+                let extent = if max > min { max - min } else { 1.0 };
+                (min - extent * self.margin, max + extent * self.margin)
+            }
+            _ => default,
+        };
+        HeatmapBounds {
+            x: fit(0, DEFAULT_BOUNDS.x),
+            y: fit(1, DEFAULT_BOUNDS.y),
+        }
+    }
+
+    // the center of every cell, row by row
+    pub fn grid(&self, bounds: &HeatmapBounds) -> Vec<[f64; 2]> {
+        let n = self.resolution;
+        let step =
+            |(min, max): (f64, f64), i: usize| min + (i as f64 + 0.5) * (max - min) / n as f64;
+        (0..n * n)
+            .map(|i| [step(bounds.x, i % n), step(bounds.y, i / n)])
+            .collect()
+    }
+
+    pub fn heatmap(&self, nn: &AnyNetwork) -> Heatmap {
+        let bounds = self.bounds(nn);
+        let mut values = nn.predict_many(&self.grid(&bounds)).to_rows();
+        let (range, surfaces) = match nn.task_type() {
+            TaskType::Regression => (Some(scale_heatmap(&mut values)), None),
+            TaskType::Classification => (None, Some(HeatmapSurfaces::new(&values, true))),
+            TaskType::MultiLabel => (None, Some(HeatmapSurfaces::new(&values, false))),
+        };
+        Heatmap {
+            resolution: self.resolution,
+            bounds,
+            values,
+            range,
+            surfaces,
+        }
+    }
+
+    /**
+     * One map per hidden neuron with neuron_resolution cells per axis.
+     */
+    pub fn neuron_heatmaps(&self, nn: &AnyNetwork) -> NeuronHeatmaps {
+        let bounds = self.bounds(nn);
+        let grid = HeatmapSettings {
+            resolution: self.neuron_resolution,
+            ..*self
+        }
+        .grid(&bounds);
+        let layers = nn
+            .hidden_outputs_many(&grid)
+            .iter()
+            .map(|outputs| {
+                (0..outputs.cols())
+                    .map(|neuron| outputs.iter_rows().map(|y| y[neuron]).collect())
+                    .collect()
+            })
+            .collect();
+        NeuronHeatmaps {
+            resolution: self.neuron_resolution,
+            bounds,
+            layers,
+        }
+    }
+
+    // whether learn should recompute the heatmap after this epoch
+    pub fn is_due(&self, epoch: usize) -> bool {
+        self.update_interval > 0 && epoch.is_multiple_of(self.update_interval)
+    }
+}
+
+impl HeatmapSurfaces {
+    /**
+     * exclusive classes read every cell as one distribution, otherwise every output is the
+     * probability of its own label.
+     */
+    pub fn new(probabilities: &[Vec<f64>], exclusive: bool) -> Self {
+        let mut surfaces = HeatmapSurfaces::default();
+        probabilities.iter().for_each(|p| {
+            let (class, confidence, entropy, margin) = if exclusive {
+                class_uncertainty(p)
+            } else {
+                label_uncertainty(p)
+            };
+            surfaces.predicted_class.push(class);
+            surfaces.confidence.push(confidence);
+            surfaces.entropy.push(entropy);
+            surfaces.margin.push(margin);
+        });
+        surfaces
+    }
+}
+
+// (predicted class, confidence, normalized entropy, margin) of one distribution
+fn class_uncertainty(p: &[f64]) -> (usize, f64, f64, f64) {
+    let (mut class, mut first, mut second) = (0, f64::NEG_INFINITY, f64::NEG_INFINITY);
+    p.iter().enumerate().for_each(|(i, &v)| {
+        if v > first {
+            (class, first, second) = (i, v, first);
+        } else if v > second {
+            second = v;
+        }
+    });
+    let entropy = if p.len() > 1 {
+        p.iter().map(|&v| plogp(v)).sum::<f64>() / (p.len() as f64).ln()
+    } else {
+        0.0
+    };
+    (class, first, entropy, (first - second.max(0.0)).max(0.0))
+}
+
+// the same for independent labels, averaged over the labels
+fn label_uncertainty(p: &[f64]) -> (usize, f64, f64, f64) {
+    let (class, _) = p
+        .iter()
+        .enumerate()
+        .fold((0, f64::NEG_INFINITY), |best, (i, &v)| {
+            if v > best.1 {
+                (i, v)
+            } else {
+                best
+            }
+        });
+    let n = p.len().max(1) as f64;
+    let confidence = p.iter().map(|&v| v.max(1.0 - v)).sum::<f64>() / n;
+    let entropy = p
+        .iter()
+        .map(|&v| (plogp(v) + plogp(1.0 - v)) / 2f64.ln())
+        .sum::<f64>()
+        / n;
+    let margin = p.iter().map(|&v| (2.0 * v - 1.0).abs()).sum::<f64>() / n;
+    (class, confidence, entropy, margin)
+}
+
+// -p ln p, 0 for p = 0
+fn plogp(p: f64) -> f64 {
+    if p > 0.0 {
+        -p * p.ln()
+    } else {
+        0.0
+    }
+}
+
+/**
+ * Rescales a regression value surface to 0..1 so it renders like class probabilities.
+ *
+ * Returns the (min, max) predicted value.
+ */
+fn scale_heatmap(heatmap: &mut [Vec<f64>]) -> (f64, f64) {
+    let (min, max) = heatmap
+        .iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &v| {
+            (min.min(v), max.max(v))
+        });
+    let range = if max > min { max - min } else { 1.0 };
+    heatmap
+        .iter_mut()
+        .flatten()
+        .for_each(|v| *v = (*v - min) / range);
+    (min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_covers_the_cell_centers() {
+        let settings = HeatmapSettings {
+            resolution: 4,
+            bounds: Some(HeatmapBounds {
+                x: (0.0, 8.0),
+                y: (-4.0, 0.0),
+            }),
+            ..Default::default()
+        };
+        let grid = settings.grid(&settings.bounds.unwrap());
+        assert_eq!(grid.len(), 16);
+        assert_eq!(grid[0], [1.0, -3.5]);
+        assert_eq!(grid[3], [7.0, -3.5]);
+        assert_eq!(grid[15], [7.0, -0.5]);
+    }
+
+    #[test]
+    fn uniform_cells_are_the_most_uncertain() {
+        let surfaces = HeatmapSurfaces::new(&[vec![0.1, 0.7, 0.2], vec![1.0 / 3.0; 3]], true);
+        assert_eq!(surfaces.predicted_class[0], 1);
+        assert_eq!(surfaces.confidence[0], 0.7);
+        assert!((surfaces.margin[0] - 0.5).abs() < 1e-12);
+        assert!((surfaces.entropy[1] - 1.0).abs() < 1e-12);
+        assert!(surfaces.margin[1].abs() < 1e-12);
+        assert!(surfaces.entropy[0] < surfaces.entropy[1]);
+    }
+}
@@ -1,23 +1,50 @@
 use calamine::{open_workbook, DataType, HeaderRow, Reader, Xlsx};
+use events::SentState;
+use heatmap::{Heatmap, HeatmapBounds, HeatmapSettings, HeatmapSurfaces, NeuronHeatmaps};
 use lazy_static::lazy_static;
-use rayon::prelude::*;
-use std::{sync::RwLock, thread::sleep, time::Duration};
+use networks::{
+    EpochValues, NamedNetwork, NetworkComparison, NetworkEvaluation, NetworkSummary, Networks,
+    TrainingHistory,
+};
+use runs::{RunComparison, RunRecord, RunStore, RunSummary};
+use search::{SearchResult, SearchSettings};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread::sleep,
+    time::Duration,
+};
 use tauri::{AppHandle, Builder, Emitter, Manager};
 
-use nn::{activation_functions::ActivationFunction, layer::LayerType, NeuralNetwork};
+use nn::{
+    activation_functions::{
+        validate_activation_function, ActivationFunction, ActivationParameters,
+    },
+    any::AnyNetwork,
+    calibration::{CalibrationMethod, CalibrationReport},
+    curves::CurveReport,
+    explain::{AttributionSettings, Attributions, PermutationImportance, Saliency},
+    float::{Float, Precision},
+    layer::LayerType,
+    metrics::{EvaluationReport, Metric, MultiLabelReport, RegressionReport},
+    model::Model,
+    stats::{NetworkStats, StatsThresholds},
+    NeuralNetwork, Split, TaskType,
+};
 
-mod nn;
+pub mod cli;
+mod events;
+mod heatmap;
+mod networks;
+pub mod nn;
+mod runs;
+mod search;
+mod tuning;
 
 lazy_static! {
-    static ref HEATMAP_DATA: Vec<Vec<f64>> = {
-        let mut data = Vec::new();
-        for i in 0..2500 {
-            let x1 = (i as f64) % 50.0 * 2.0;
-            let x2 = ((i as f64) / 50.0 * 2.0).floor();
-            data.push(vec![x1, x2]);
-        }
-        data
-    };
     static ref DEFAULT_NN: NeuralNetwork = NeuralNetwork::new::<f64, f64>(
         2,
         vec![8, 4],
@@ -39,8 +66,22 @@ lazy_static! {
         cross_entropy_loss: None,
         confusion_matrix: None,
         predicted: None,
+        attributions: None,
         parameters: None,
         heatmap: Some(get_dummy_heatmap_data()),
+        multi_label_report: None,
+        regression_report: None,
+        heatmap_range: None,
+        heatmap_bounds: None,
+        heatmap_resolution: None,
+        heatmap_surfaces: None,
+        neuron_heatmaps: None,
+        heatmap_version: 0,
+        evaluation: None,
+        calibration: None,
+        metric_history: None,
+        layer_stats: None,
+        run_id: None,
     };
 }
 
@@ -58,8 +99,34 @@ struct ClientState {
     confusion_matrix: Option<Vec<Vec<usize>>>,
     cross_entropy_loss: Option<f64>,
     predicted: Option<Vec<f64>>,
+    // explanation of the predicted sample when predict was asked for one
+    attributions: Option<Attributions>,
     parameters: Option<Vec<Vec<(Vec<f64>, f64, LayerType)>>>,
     heatmap: Option<Vec<Vec<f64>>>,
+    multi_label_report: Option<MultiLabelReport>,
+    regression_report: Option<RegressionReport>,
+    // (min, max) predicted value when the heatmap shows a regression surface scaled to 0..1
+    heatmap_range: Option<(f64, f64)>,
+    // input space covered by the heatmap and its cells per axis
+    heatmap_bounds: Option<HeatmapBounds>,
+    heatmap_resolution: Option<usize>,
+    // predicted class, confidence, entropy and margin over the heatmap grid
+    heatmap_surfaces: Option<HeatmapSurfaces>,
+    // what every hidden neuron responds to over the heatmap bounds
+    neuron_heatmaps: Option<NeuronHeatmaps>,
+    // counts the heatmap updates, so delta updates only resend a changed heatmap
+    #[serde(skip)]
+    heatmap_version: u64,
+    // one report per non empty split (training, validation, testing)
+    evaluation: Option<Vec<EvaluationReport>>,
+    // reliability diagram of the testing data
+    calibration: Option<CalibrationReport>,
+    // per epoch values of the tracked metrics
+    metric_history: Option<BTreeMap<Metric, Vec<f64>>>,
+    // weight, gradient and activation statistics of every layer after the last epoch
+    layer_stats: Option<NetworkStats>,
+    // id of the stored run the network comes from
+    run_id: Option<String>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -67,6 +134,8 @@ struct ClientState {
 struct SettingsLayer {
     neurons_count: usize,
     activation_function: ActivationFunction,
+    #[serde(default)]
+    activation_parameters: ActivationParameters,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -84,14 +153,127 @@ struct Settings {
     noise: f64,
     density: f64,
     scale: (f64, f64),
+    #[serde(default)]
+    label_smoothing: f64,
+    // classification, multi-label or regression
+    #[serde(default)]
+    task_type: TaskType,
+    // per label decision thresholds in multi-label mode
+    #[serde(default)]
+    label_thresholds: Vec<f64>,
+    // metrics recorded after every epoch in addition to the MSE
+    #[serde(default)]
+    tracked_metrics: Vec<Metric>,
+    // samples per weight update, 0 and 1 train one sample at a time
+    #[serde(default)]
+    batch_size: usize,
+    // makes the weight initialization, data order and training reproducible
+    #[serde(default)]
+    seed: Option<u64>,
+    // f32 halves the memory of the weights and data and trains faster
+    #[serde(default)]
+    precision: Precision,
+    // resolution, bounds and update interval of the decision boundary heatmap
+    #[serde(default)]
+    heatmap: HeatmapSettings,
+    // client state updates per second while learning, 0 uses 30
+    #[serde(default)]
+    frame_rate: f64,
+    // limits past which the layer statistics warn about vanishing gradients, dead units...
+    #[serde(default)]
+    stats_thresholds: StatsThresholds,
 }
 
 #[tauri::command]
 async fn reset(app: AppHandle, settings: Settings) -> Result<(), String> {
+    let nn = build_network(&settings)?;
+
     // aquire the state
     let state = app.state::<RwLock<AppState>>();
     let mut state = state.write().unwrap();
+    // the other networks start over on the new data
+    rebuild_others(&mut state.networks, Some(&settings))?;
+
+    state.tracked_metrics = settings.tracked_metrics.clone();
+    state.heatmap = settings.heatmap;
+    state.frame_rate = settings.frame_rate;
+    state.stats_thresholds = settings.stats_thresholds;
+    state.layer_stats_history.clear();
+    *state.networks.active_mut() = NamedNetwork::new(nn, Some(settings));
+
+    // no run id either, the new network does not come from a stored run
+    state.client_state = DEFAULT_STATS.clone();
+    state.client_state.run_id = None;
+    emit_client_state(&app, &mut state);
+
+    Ok(())
+}
+
+/**
+ * Puts the networks other than the active one on the data of the settings, so they compare on
+ * identical splits.
+ *
+ * loaded models have no settings to rebuild them and are removed, so is every other network
+ * when there is no data (a loaded model file). Nothing changes when a network fails to build.
+ */
+fn rebuild_others(networks: &mut Networks, data: Option<&Settings>) -> Result<(), String> {
+    let active = networks.active_name().to_string();
+    let mut rebuilt = vec![];
+    let mut removed = vec![];
+    for (name, network) in networks.iter().filter(|(name, _)| **name != active) {
+        match (data, &network.settings) {
+            (Some(data), Some(settings)) => {
+                let settings = with_data_of(settings, data);
+                let nn = build_network(&settings)?;
+                rebuilt.push((name.clone(), NamedNetwork::new(nn, Some(settings))));
+            }
+            _ => removed.push(name.clone()),
+        }
+    }
+    removed.iter().try_for_each(|name| networks.remove(name))?;
+    rebuilt
+        .into_iter()
+        .try_for_each(|(name, network)| networks.insert(name, network))
+}
 
+/**
+ * The settings with the data (and task type) of other settings, so networks compare on identical splits.
+ */
+fn with_data_of(settings: &Settings, data: &Settings) -> Settings {
+    Settings {
+        training_data: data.training_data.clone(),
+        validation_data: data.validation_data.clone(),
+        testing_data: data.testing_data.clone(),
+        noise: data.noise,
+        density: data.density,
+        scale: data.scale,
+        task_type: data.task_type,
+        ..settings.clone()
+    }
+}
+
+/**
+ * Validates the settings and builds the untrained network with its data.
+ */
+fn build_network(settings: &Settings) -> Result<AnyNetwork, String> {
+    // report invalid custom activation functions to the UI
+    settings
+        .hidden_layers
+        .iter()
+        .chain(std::iter::once(&settings.output_layer))
+        .try_for_each(|l| validate_activation_function(&l.activation_function))?;
+    settings.heatmap.validate()?;
+
+    // drawn here so the run record can store the seed of every network
+    let seed = Some(settings.seed.unwrap_or_else(rand::random));
+    Ok(match settings.precision {
+        Precision::F32 => network::<f32>(settings, seed).into(),
+        Precision::F64 => network::<f64>(settings, seed).into(),
+    })
+}
+
+// the network of the settings computing in T
+fn network<T: Float>(settings: &Settings, seed: Option<u64>) -> NeuralNetwork<T> {
     let hidden_layers_topology = settings
         .hidden_layers
         .iter()
@@ -101,46 +283,48 @@ async fn reset(app: AppHandle, settings: Settings) -> Result<(), String> {
     let hidden_layers_activation_functions = settings
         .hidden_layers
         .iter()
-        .map(|l| l.activation_function)
+        .map(|l| l.activation_function.clone())
         .collect::<Vec<_>>();
 
-    state.nn = NeuralNetwork::new(
+    let split = |data: &[Vec<f64>]| {
+        data.iter()
+            .map(|v| {
+                let x = v.split_at(2);
+                (x.0.to_vec(), x.1.to_vec())
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let nn = NeuralNetwork::with_seed(
+        seed,
         2,
         hidden_layers_topology,
         &hidden_layers_activation_functions,
         settings.output_layer.neurons_count,
-        settings.output_layer.activation_function,
+        settings.output_layer.activation_function.clone(),
         settings.alpha,
-        settings
-            .training_data
-            .into_iter()
-            .map(|v| {
-                let x = v.split_at(2);
-                (x.0.to_vec(), x.1.to_vec())
-            })
-            .collect::<Vec<_>>(),
-        settings
-            .validation_data
-            .into_iter()
-            .map(|v| {
-                let x = v.split_at(2);
-                (x.0.to_vec(), x.1.to_vec())
-            })
-            .collect::<Vec<_>>(),
-        settings
-            .testing_data
-            .into_iter()
-            .map(|v| {
-                let x = v.split_at(2);
-                (x.0.to_vec(), x.1.to_vec())
-            })
-            .collect::<Vec<_>>(),
+        split(&settings.training_data),
+        split(&settings.validation_data),
+        split(&settings.testing_data),
     );
+    configure(nn, settings)
+}
 
-    state.client_state = DEFAULT_STATS.clone();
-    reset_client_state(&app, Some(&state.client_state));
-
-    Ok(())
+// applies the settings the network constructor does not take
+fn configure<T: Float>(mut nn: NeuralNetwork<T>, settings: &Settings) -> NeuralNetwork<T> {
+    nn.set_activation_parameters(
+        &settings
+            .hidden_layers
+            .iter()
+            .chain(std::iter::once(&settings.output_layer))
+            .map(|l| l.activation_parameters)
+            .collect::<Vec<_>>(),
+    );
+    nn.set_label_smoothing(settings.label_smoothing);
+    nn.set_task_type(settings.task_type);
+    nn.set_label_thresholds(settings.label_thresholds.clone());
+    nn.set_batch_size(settings.batch_size);
+    nn
 }
 
 #[tauri::command]
@@ -149,17 +333,15 @@ async fn stop(app: AppHandle) -> Result<(), String> {
         let state = app.state::<RwLock<AppState>>();
         let mut state = state.write().unwrap();
         state.client_state.is_learning = false;
+        state.search_stopped.store(true, Ordering::Relaxed);
     }
     // allow the learn function to break the loop
     sleep(Duration::from_millis(100));
     {
         let state = app.state::<RwLock<AppState>>();
         let mut state = state.write().unwrap();
-        state.client_state.confusion_matrix = Some(state.nn.confusion_matrix());
-        state.client_state.cross_entropy_loss = Some(state.nn.cross_entropy_loss());
-        state.client_state.parameters = Some(state.nn.get_parameters());
-        app.emit("UPDATE_CLIENT_STATE", state.client_state.clone())
-            .unwrap();
+        update_evaluation(&mut state);
+        emit_client_state(&app, &mut state);
     }
     Ok(())
 }
@@ -170,13 +352,17 @@ async fn learn(app: AppHandle, max_epoch_count: usize, desired_mse: f64) -> Resu
         // aquire the state and update the training data
         let state = app.state::<RwLock<AppState>>();
         let mut state = state.write().unwrap();
-        state.client_state = DEFAULT_STATS.clone();
+        // the history carries on without a reset, so a saved run covers every epoch of its model
+        show_active_network(&mut state);
         state.client_state.is_learning = true;
-        reset_client_state(&app, Some(&state.client_state));
+        emit_client_state(&app, &mut state);
         // state aquiration ends here
     }
 
-    let _ = (0..=max_epoch_count).into_iter().try_for_each(|epoch| {
+    let mut diverged = false;
+    // the heatmaps are only computed when an update is about to be sent
+    let mut heatmap_pending = false;
+    let _ = (0..=max_epoch_count).into_iter().try_for_each(|_| {
         {
             let state = app.state::<RwLock<AppState>>();
             let mut state = state.write().unwrap();
@@ -184,23 +370,13 @@ async fn learn(app: AppHandle, max_epoch_count: usize, desired_mse: f64) -> Resu
                 return Err("Learning stopped!".to_string());
             }
 
-            let (mse, mse_validation) = state.nn.epoch();
-            state.client_state.epoch = Some(epoch);
-            state.client_state.mse = Some(mse);
-            state.client_state.mse_validation = Some(mse_validation);
-            if let Some(v) = state.client_state.mse_history.as_mut() {
-                v.push(mse);
-            } else {
-                state.client_state.mse_history = Some(vec![mse]);
-            }
-            if let Some(v) = state.client_state.mse_validation_history.as_mut() {
-                v.push(mse_validation);
-            } else {
-                state.client_state.mse_validation_history = Some(vec![mse_validation]);
-            }
-            state.client_state.heatmap = Some(get_heatmap_data(&state.nn));
-            app.emit("UPDATE_CLIENT_STATE", &state.client_state)
-                .unwrap();
+            let tracked_metrics = state.tracked_metrics.clone();
+            let Some(values) = state.networks.active_mut().epoch(&tracked_metrics) else {
+                diverged = true;
+                return Err("Training diverged!".to_string());
+            };
+            let mse = values.mse;
+            show_epoch(&app, &mut state, values, &mut heatmap_pending);
             if mse <= desired_mse {
                 return Err("Desired MSE reached!".to_string());
             }
@@ -215,22 +391,142 @@ async fn learn(app: AppHandle, max_epoch_count: usize, desired_mse: f64) -> Resu
         // aquire the state and update the training data
         let state = app.state::<RwLock<AppState>>();
         let mut state = state.write().unwrap();
-        let confusion_matrix = state.nn.confusion_matrix();
-        state.client_state = ClientState {
-            is_learning: false,
-            confusion_matrix: Some(confusion_matrix),
-            cross_entropy_loss: Some(state.nn.cross_entropy_loss()),
-            parameters: Some(state.nn.get_parameters()),
-            ..state.client_state.clone()
+        state.client_state.is_learning = false;
+        update_evaluation(&mut state);
+        update_heatmap(&mut state);
+        // a diverged network cannot be reloaded, its weights are not finite
+        let saved = if diverged {
+            Ok(None)
+        } else {
+            save_run(&app, state.networks.active())
         };
-        app.emit("UPDATE_CLIENT_STATE", state.client_state.clone())
-            .unwrap();
+        if let Ok(Some(id)) = &saved {
+            state.client_state.run_id = Some(id.clone());
+        }
+        emit_client_state(&app, &mut state);
+        saved?;
         // state aquiration ends here
     }
 
+    if diverged {
+        return Err(
+            "Training diverged: the MSE is not finite, check the activation functions".to_string(),
+        );
+    }
+
     Ok(())
 }
 
+/**
+ * Records the settings, data fingerprint, histories, evaluation and model of a trained network.
+ *
+ * Returns the id of the run, None when the network has no data or did not train.
+ */
+fn save_run(app: &AppHandle, network: &NamedNetwork) -> Result<Option<String>, String> {
+    let (Some(settings), history) = (&network.settings, &network.history) else {
+        return Ok(None);
+    };
+    if history.mse.is_empty() {
+        return Ok(None);
+    }
+    let store = run_store(app)?;
+    let (id, created_at) = store.next_id();
+    let evaluation = NetworkEvaluation::new(&network.nn);
+    let record = RunRecord {
+        id: id.clone(),
+        created_at,
+        settings: settings.clone(),
+        fingerprint: runs::fingerprint(&[
+            &settings.training_data,
+            &settings.validation_data,
+            &settings.testing_data,
+        ]),
+        seed: network.nn.seed(),
+        epochs: history.mse.len(),
+        mse_history: history.mse.clone(),
+        mse_validation_history: history.mse_validation.clone(),
+        metric_history: history.metrics.clone(),
+        evaluation: evaluation.evaluation,
+        multi_label_report: evaluation.multi_label_report,
+        regression_report: evaluation.regression_report,
+        model: network.nn.to_model(),
+    };
+    store.save(&record)?;
+    Ok(Some(id))
+}
+
+// runs are stored in the application data directory
+fn run_store(app: &AppHandle) -> Result<RunStore, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(RunStore::new(dir.join("runs")))
+}
+
+/**
+ * Summaries of the stored runs, oldest first.
+ */
+#[tauri::command]
+async fn list_runs(app: AppHandle) -> Result<Vec<RunSummary>, String> {
+    run_store(&app)?.list()
+}
+
+/**
+ * Restores the network, data, histories and evaluation of a stored run.
+ *
+ * Returns its settings so the frontend can show them.
+ */
+#[tauri::command]
+async fn load_run(app: AppHandle, id: String) -> Result<Settings, String> {
+    let record = run_store(&app)?.load(&id)?;
+    // the seed of the run shuffles the data like it was
+    let mut nn = build_network(&Settings {
+        seed: record.seed,
+        ..record.settings.clone()
+    })?;
+    nn.load_parameters(&record.model)?;
+
+    let state = app.state::<RwLock<AppState>>();
+    let mut state = state.write().unwrap();
+    // the other networks are compared on the data of the run
+    rebuild_others(&mut state.networks, Some(&record.settings))?;
+    state.tracked_metrics = record.settings.tracked_metrics.clone();
+    state.heatmap = record.settings.heatmap;
+    state.frame_rate = record.settings.frame_rate;
+    state.stats_thresholds = record.settings.stats_thresholds;
+    state.layer_stats_history.clear();
+    *state.networks.active_mut() = NamedNetwork {
+        nn,
+        settings: Some(record.settings.clone()),
+        history: TrainingHistory {
+            mse: record.mse_history,
+            mse_validation: record.mse_validation_history,
+            metrics: record.metric_history,
+            diverged: false,
+        },
+    };
+    show_active_network(&mut state);
+    state.client_state.run_id = Some(record.id);
+    emit_client_state(&app, &mut state);
+    Ok(record.settings)
+}
+
+/**
+ * Histories aligned by epoch and the settings that differ between stored runs.
+ */
+#[tauri::command]
+async fn compare_runs(app: AppHandle, ids: Vec<String>) -> Result<RunComparison, String> {
+    let store = run_store(&app)?;
+    let records = ids
+        .iter()
+        .map(|id| store.load(id))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(RunComparison::new(&records))
+}
+
+#[tauri::command]
+async fn delete_run(app: AppHandle, id: String) -> Result<(), String> {
+    run_store(&app)?.delete(&id)
+}
+
 #[tauri::command]
 async fn load_custom_data(app: AppHandle, path: String) -> Result<Vec<Vec<f64>>, String> {
     reset_client_state(&app, None);
@@ -254,29 +550,498 @@ async fn load_custom_data(app: AppHandle, path: String) -> Result<Vec<Vec<f64>>,
     Err("Cannot load data".to_string())
 }
 
+/**
+ * Predicts one sample, optionally explaining it with per input attributions.
+ *
+ * the attributions are returned and stored in the client state next to the probabilities.
+ */
 #[tauri::command]
-async fn predict(app: AppHandle, inputs: Vec<f64>) -> Result<(), String> {
+async fn predict(
+    app: AppHandle,
+    inputs: Vec<f64>,
+    attributions: Option<AttributionSettings>,
+) -> Result<Option<Attributions>, String> {
     let state = app.state::<RwLock<AppState>>();
     let mut state = state.write().unwrap();
-    state.client_state.predicted = Some(state.nn.predict(&inputs));
-    app.emit("UPDATE_CLIENT_STATE", state.client_state.clone())
-        .unwrap();
+    let attributions = attributions
+        .map(|settings| state.nn().attributions(&inputs, &settings))
+        .transpose()?;
+    state.client_state.predicted = Some(state.nn().predict(&inputs));
+    state.client_state.attributions = attributions.clone();
+    emit_client_state(&app, &mut state);
 
+    Ok(attributions)
+}
+
+/**
+ * Writes the evaluation reports of the last training as JSON.
+ */
+#[tauri::command]
+async fn export_evaluation(app: AppHandle, path: String) -> Result<(), String> {
+    let state = app.state::<RwLock<AppState>>();
+    let state = state.read().unwrap();
+    let evaluation = state
+        .client_state
+        .evaluation
+        .as_ref()
+        .ok_or("No evaluation available, train the network first")?;
+    let json = serde_json::to_string_pretty(evaluation).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())
+}
+
+/**
+ * Writes the layer statistics of every epoch of the last training as JSON.
+ */
+#[tauri::command]
+async fn export_layer_stats(app: AppHandle, path: String) -> Result<(), String> {
+    let state = app.state::<RwLock<AppState>>();
+    let state = state.read().unwrap();
+    if state.layer_stats_history.is_empty() {
+        return Err("No layer statistics available, train the network first".to_string());
+    }
+    let json =
+        serde_json::to_string_pretty(&state.layer_stats_history).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())
+}
+
+/**
+ * ROC and precision-recall curves (with AUC per class) of the validation and testing data.
+ */
+#[tauri::command]
+async fn get_curves(app: AppHandle) -> Result<Vec<CurveReport>, String> {
+    let state = app.state::<RwLock<AppState>>();
+    let state = state.read().unwrap();
+    if state.nn().task_type() == TaskType::Regression {
+        return Err("ROC and precision-recall curves need a classification task".to_string());
+    }
+    Ok(state.nn().curve_reports())
+}
+
+/**
+ * Fits temperature or Platt scaling on the validation data.
+ *
+ * predictions, metrics and the heatmap use the calibrated probabilities afterwards.
+ */
+#[tauri::command]
+async fn calibrate(app: AppHandle, method: CalibrationMethod) -> Result<(), String> {
+    let state = app.state::<RwLock<AppState>>();
+    let mut state = state.write().unwrap();
+    state.nn_mut().calibrate(method)?;
+    update_evaluation(&mut state);
+    update_heatmap(&mut state);
+    emit_client_state(&app, &mut state);
     Ok(())
 }
 
-fn get_heatmap_data(nn: &NeuralNetwork) -> Vec<Vec<f64>> {
-    HEATMAP_DATA
-        .par_iter()
-        .map(|inputs| nn.predict(inputs))
-        .collect::<Vec<_>>()
+/**
+ * Gradients of every output with respect to the inputs of one sample.
+ */
+#[tauri::command]
+async fn get_saliency(app: AppHandle, inputs: Vec<f64>) -> Result<Saliency, String> {
+    let state = app.state::<RwLock<AppState>>();
+    let state = state.read().unwrap();
+    state.nn().saliency(&inputs)
+}
+
+/**
+ * Accuracy (MSE for regression) lost on the testing data when each input is shuffled.
+ */
+#[tauri::command]
+async fn get_permutation_importance(
+    app: AppHandle,
+    repeats: Option<usize>,
+) -> Result<PermutationImportance, String> {
+    let state = app.state::<RwLock<AppState>>();
+    let state = state.read().unwrap();
+    state
+        .nn()
+        .permutation_importance(Split::Testing, repeats.unwrap_or(5))
+}
+
+/**
+ * Writes the trained network (weights, activations, normalization, calibration) as JSON.
+ */
+#[tauri::command]
+async fn save_model(app: AppHandle, path: String) -> Result<(), String> {
+    let state = app.state::<RwLock<AppState>>();
+    let state = state.read().unwrap();
+    let json = serde_json::to_string_pretty(&state.nn().to_model()).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())
+}
+
+/**
+ * Replaces the network with a saved model, in the precision it was trained with.
+ *
+ * the loaded network has no data, only predictions and the heatmap are available. The other
+ * networks are removed since there is no data to compare them on.
+ */
+#[tauri::command]
+async fn load_model(app: AppHandle, path: String) -> Result<(), String> {
+    let json = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let model: Model = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    let nn = AnyNetwork::from_model(&model)?;
+
+    let state = app.state::<RwLock<AppState>>();
+    let mut state = state.write().unwrap();
+    rebuild_others(&mut state.networks, None)?;
+    *state.networks.active_mut() = NamedNetwork::new(nn, None);
+    state.client_state = DEFAULT_STATS.clone();
+    state.client_state.parameters = Some(state.nn().get_parameters());
+    update_heatmap(&mut state);
+    emit_client_state(&app, &mut state);
+    Ok(())
+}
+
+/**
+ * Computes the heatmap on demand, optionally with other settings than the training ones.
+ *
+ * the prediction runs on a snapshot of the weights, so large grids do not block training.
+ */
+#[tauri::command]
+async fn get_heatmap(app: AppHandle, settings: Option<HeatmapSettings>) -> Result<Heatmap, String> {
+    let (nn, heatmap_settings) = {
+        let state = app.state::<RwLock<AppState>>();
+        let state = state.read().unwrap();
+        (
+            AnyNetwork::from_model(&state.nn().to_model())?,
+            state.heatmap,
+        )
+    };
+    let settings = settings.unwrap_or(heatmap_settings);
+    settings.validate()?;
+    Ok(settings.heatmap(&nn))
+}
+
+/**
+ * Outputs of every hidden neuron over the heatmap bounds, resolution cells per axis.
+ *
+ * like get_heatmap this runs on a snapshot of the weights.
+ */
+#[tauri::command]
+async fn get_neuron_heatmaps(
+    app: AppHandle,
+    resolution: Option<usize>,
+) -> Result<NeuronHeatmaps, String> {
+    let (nn, heatmap_settings) = {
+        let state = app.state::<RwLock<AppState>>();
+        let state = state.read().unwrap();
+        (
+            AnyNetwork::from_model(&state.nn().to_model())?,
+            state.heatmap,
+        )
+    };
+    let settings = HeatmapSettings {
+        neuron_resolution: resolution.unwrap_or(match heatmap_settings.neuron_resolution {
+            0 => HeatmapSettings::default().neuron_resolution,
+            resolution => resolution,
+        }),
+        ..heatmap_settings
+    };
+    settings.validate()?;
+    Ok(settings.neuron_heatmaps(&nn))
+}
+
+/**
+ * The whole client state, for a frontend that missed updates or was reloaded.
+ *
+ * the following delta updates continue from it.
+ */
+#[tauri::command]
+async fn resync_client_state(app: AppHandle) -> Result<ClientState, String> {
+    let state = app.state::<RwLock<AppState>>();
+    let mut state = state.write().unwrap();
+    state.sent = SentState::synced(&state.client_state);
+    Ok(state.client_state.clone())
+}
+
+/**
+ * The networks held by the backend, the active one is shown by the client state.
+ */
+#[tauri::command]
+async fn list_networks(app: AppHandle) -> Result<Vec<NetworkSummary>, String> {
+    let state = app.state::<RwLock<AppState>>();
+    let state = state.read().unwrap();
+    Ok(state.networks.summaries())
+}
+
+/**
+ * Adds (or replaces) a network built from the settings on the data of the last reset.
+ */
+#[tauri::command]
+async fn add_network(app: AppHandle, name: String, settings: Settings) -> Result<(), String> {
+    let state = app.state::<RwLock<AppState>>();
+    let mut state = state.write().unwrap();
+    if name == state.networks.active_name() {
+        return Err("Use reset to rebuild the active network".to_string());
+    }
+    let data = state
+        .networks
+        .active()
+        .settings
+        .as_ref()
+        .ok_or("Reset the network with data before adding others")?;
+    let settings = with_data_of(&settings, data);
+    let nn = build_network(&settings)?;
+    state
+        .networks
+        .insert(name, NamedNetwork::new(nn, Some(settings)))
+}
+
+#[tauri::command]
+async fn remove_network(app: AppHandle, name: String) -> Result<(), String> {
+    let state = app.state::<RwLock<AppState>>();
+    let mut state = state.write().unwrap();
+    state.networks.remove(&name)
+}
+
+/**
+ * Makes another network the active one and shows its histories, evaluation and heatmap.
+ */
+#[tauri::command]
+async fn select_network(app: AppHandle, name: String) -> Result<(), String> {
+    let state = app.state::<RwLock<AppState>>();
+    let mut state = state.write().unwrap();
+    state.networks.set_active(&name)?;
+    state.layer_stats_history.clear();
+    show_active_network(&mut state);
+    emit_client_state(&app, &mut state);
+    Ok(())
+}
+
+/**
+ * Trains all networks side by side, one epoch of each at a time, until every one of them
+ * reached max_epoch_count, desired_mse or diverged. stop interrupts them like learn.
+ *
+ * every trained network is stored as a run.
+ */
+#[tauri::command]
+async fn train_networks(
+    app: AppHandle,
+    max_epoch_count: usize,
+    desired_mse: f64,
+) -> Result<NetworkComparison, String> {
+    {
+        let state = app.state::<RwLock<AppState>>();
+        let mut state = state.write().unwrap();
+        // the histories carry on without a reset, like in learn
+        show_active_network(&mut state);
+        state.client_state.is_learning = true;
+        emit_client_state(&app, &mut state);
+    }
+
+    let mut heatmap_pending = false;
+    for _ in 0..=max_epoch_count {
+        {
+            let state = app.state::<RwLock<AppState>>();
+            let mut state = state.write().unwrap();
+            let tracked_metrics = state.tracked_metrics.clone();
+            let epochs = state.networks.active().history.mse.len();
+            if !state.client_state.is_learning
+                || !state.networks.epoch(&tracked_metrics, desired_mse)
+            {
+                break;
+            }
+            // the active network stops earlier than the others when it reached desired_mse
+            let history = &state.networks.active().history;
+            if history.mse.len() > epochs {
+                let values = history.last().unwrap();
+                show_epoch(&app, &mut state, values, &mut heatmap_pending);
+            }
+        }
+        // allow the frontend to invoke stop/reset commands
+        sleep(Duration::from_nanos(1000));
+    }
+
+    let state = app.state::<RwLock<AppState>>();
+    let mut state = state.write().unwrap();
+    let saved = state
+        .networks
+        .iter()
+        .filter(|(_, network)| !network.history.diverged)
+        .map(|(name, network)| Ok((name.clone(), save_run(&app, network)?)))
+        .collect::<Result<Vec<_>, String>>();
+    show_active_network(&mut state);
+    if let Ok(saved) = &saved {
+        let active = state.networks.active_name();
+        state.client_state.run_id = saved
+            .iter()
+            .find(|(name, _)| name == active)
+            .and_then(|(_, id)| id.clone());
+    }
+    emit_client_state(&app, &mut state);
+    saved?;
+    Ok(state.networks.comparison())
+}
+
+/**
+ * Histories aligned by epoch and evaluation reports of all networks.
+ */
+#[tauri::command]
+async fn compare_networks(app: AppHandle) -> Result<NetworkComparison, String> {
+    let state = app.state::<RwLock<AppState>>();
+    let state = state.read().unwrap();
+    Ok(state.networks.comparison())
+}
+
+/**
+ * Hyperparameter search over the settings of the active network, on its data.
+ *
+ * grid, random, Bayesian (TPE), successive halving or Hyperband trials are ranked by a
+ * validation metric, the best one comes with its settings and model. stop interrupts the search.
+ */
+#[tauri::command]
+async fn search_hyperparameters(
+    app: AppHandle,
+    search: SearchSettings,
+) -> Result<SearchResult, String> {
+    let (base, stopped) = {
+        let state = app.state::<RwLock<AppState>>();
+        let state = state.read().unwrap();
+        let base = state
+            .networks
+            .active()
+            .settings
+            .clone()
+            .ok_or("Reset the network with data before searching")?;
+        (base, state.search_stopped.clone())
+    };
+    stopped.store(false, Ordering::Relaxed);
+    search::search(&base, &search, &stopped)
+}
+
+/**
+ * Adds the last epoch of the active network to the client state and sends the changes.
+ *
+ * the heatmaps are only computed when they are due and an update is about to be sent.
+ */
+fn show_epoch(
+    app: &AppHandle,
+    state: &mut AppState,
+    values: EpochValues,
+    heatmap_pending: &mut bool,
+) {
+    let epoch = state.networks.active().history.mse.len() - 1;
+    let client_state = &mut state.client_state;
+    client_state.epoch = Some(epoch);
+    client_state.mse = Some(values.mse);
+    client_state.mse_validation = Some(values.mse_validation);
+    client_state
+        .mse_history
+        .get_or_insert_with(Vec::new)
+        .push(values.mse);
+    client_state
+        .mse_validation_history
+        .get_or_insert_with(Vec::new)
+        .push(values.mse_validation);
+    if !values.metrics.is_empty() {
+        let history = client_state
+            .metric_history
+            .get_or_insert_with(BTreeMap::new);
+        values.metrics.into_iter().for_each(|(metric, value)| {
+            history.entry(metric).or_default().push(value);
+        });
+    }
+    let stats = state.nn().layer_stats(&state.stats_thresholds);
+    state.layer_stats_history.push(stats.clone());
+    state.client_state.layer_stats = Some(stats);
+    *heatmap_pending |= state.heatmap.is_due(epoch);
+    if *heatmap_pending && state.sent.is_due(state.frame_rate) {
+        update_heatmap(state);
+        *heatmap_pending = false;
+    }
+    emit_client_state_delta(app, state);
+}
+
+/**
+ * Rebuilds the client state from the history and weights of the active network.
+ */
+fn show_active_network(state: &mut AppState) {
+    let history = state.networks.active().history.clone();
+    let client_state = &mut state.client_state;
+    *client_state = DEFAULT_STATS.clone();
+    if !history.mse.is_empty() {
+        client_state.epoch = Some(history.mse.len() - 1);
+        client_state.mse = history.mse.last().copied();
+        client_state.mse_validation = history.mse_validation.last().copied();
+        client_state.mse_history = Some(history.mse);
+        client_state.mse_validation_history = Some(history.mse_validation);
+        client_state.metric_history = Some(history.metrics).filter(|m| !m.is_empty());
+    }
+    update_evaluation(state);
+    update_heatmap(state);
+}
+
+/**
+ * Fills the end of training results of the client state for the current task type.
+ */
+fn update_evaluation(state: &mut AppState) {
+    let nn = &state.networks.active().nn;
+    let client_state = &mut state.client_state;
+    client_state.confusion_matrix = None;
+    client_state.multi_label_report = None;
+    client_state.regression_report = None;
+    client_state.cross_entropy_loss = None;
+    client_state.evaluation = None;
+    client_state.calibration = None;
+    match nn.task_type() {
+        TaskType::Classification => {
+            client_state.confusion_matrix = Some(nn.confusion_matrix());
+            client_state.evaluation = Some(nn.evaluation_reports());
+            client_state.cross_entropy_loss = Some(nn.cross_entropy_loss());
+            client_state.calibration = Some(nn.calibration_report(Split::Testing, 10));
+        }
+        TaskType::MultiLabel => {
+            client_state.multi_label_report = Some(nn.multi_label_report());
+            client_state.cross_entropy_loss = Some(nn.cross_entropy_loss());
+            client_state.calibration = Some(nn.calibration_report(Split::Testing, 10));
+        }
+        TaskType::Regression => {
+            client_state.regression_report = Some(nn.regression_report());
+        }
+    }
+    client_state.parameters = Some(nn.get_parameters());
+}
+
+/**
+ * Recomputes the heatmap of the client state with the current heatmap settings.
+ */
+fn update_heatmap(state: &mut AppState) {
+    let heatmap = state.heatmap.heatmap(state.nn());
+    let client_state = &mut state.client_state;
+    client_state.heatmap = Some(heatmap.values);
+    client_state.heatmap_range = heatmap.range;
+    client_state.heatmap_bounds = Some(heatmap.bounds);
+    client_state.heatmap_resolution = Some(heatmap.resolution);
+    client_state.heatmap_version += 1;
+    client_state.heatmap_surfaces = heatmap.surfaces;
+    client_state.neuron_heatmaps = if state.heatmap.neuron_resolution > 0 {
+        Some(state.heatmap.neuron_heatmaps(&state.networks.active().nn))
+    } else {
+        None
+    };
 }
 
 fn get_dummy_heatmap_data() -> Vec<Vec<f64>> {
-    (0..2500)
-        .into_par_iter()
-        .map(|_| vec![0.0, 0.0])
-        .collect::<Vec<_>>()
+    vec![vec![0.0, 0.0]; 2500]
+}
+
+/**
+ * Sends the whole client state, the following delta updates continue from it.
+ */
+fn emit_client_state(app: &AppHandle, state: &mut AppState) {
+    state.sent = SentState::synced(&state.client_state);
+    app.emit("UPDATE_CLIENT_STATE", &state.client_state)
+        .unwrap();
+}
+
+/**
+ * Sends what changed since the previous update, at most frame_rate times per second.
+ */
+fn emit_client_state_delta(app: &AppHandle, state: &mut AppState) {
+    if state.sent.is_due(state.frame_rate) {
+        let delta = state.sent.delta(&state.client_state);
+        app.emit("UPDATE_CLIENT_STATE_DELTA", delta).unwrap();
+    }
 }
 
 fn reset_client_state(app: &AppHandle, stats: Option<&ClientState>) {
@@ -288,18 +1053,46 @@ fn reset_client_state(app: &AppHandle, stats: Option<&ClientState>) {
 }
 
 struct AppState {
-    nn: NeuralNetwork,
+    networks: Networks,
     client_state: ClientState,
+    tracked_metrics: Vec<Metric>,
+    heatmap: HeatmapSettings,
+    frame_rate: f64,
+    // what the frontend already received of the client state
+    sent: SentState,
+    stats_thresholds: StatsThresholds,
+    // one entry per epoch of the active network since it was reset, selected or loaded
+    layer_stats_history: Vec<NetworkStats>,
+    // set by stop, checked by the running search trials after every epoch
+    search_stopped: Arc<AtomicBool>,
+}
+
+impl AppState {
+    // the network shown by the client state
+    fn nn(&self) -> &AnyNetwork {
+        &self.networks.active().nn
+    }
+
+    fn nn_mut(&mut self) -> &mut AnyNetwork {
+        &mut self.networks.active_mut().nn
+    }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     Builder::default()
         .setup(|app| {
-            let nn = DEFAULT_NN.clone();
+            let nn = DEFAULT_NN.clone().into();
             let state = AppState {
-                nn,
+                networks: Networks::new(NamedNetwork::new(nn, None)),
                 client_state: DEFAULT_STATS.clone(),
+                tracked_metrics: vec![],
+                heatmap: HeatmapSettings::default(),
+                frame_rate: 0.0,
+                sent: SentState::default(),
+                stats_thresholds: StatsThresholds::default(),
+                layer_stats_history: vec![],
+                search_stopped: Arc::new(AtomicBool::new(false)),
             };
             app.manage(RwLock::new(state));
             Ok(())
@@ -311,7 +1104,29 @@ pub fn run() {
             learn,
             stop,
             load_custom_data,
-            predict
+            predict,
+            export_evaluation,
+            export_layer_stats,
+            get_curves,
+            calibrate,
+            save_model,
+            load_model,
+            get_heatmap,
+            get_neuron_heatmaps,
+            resync_client_state,
+            get_saliency,
+            get_permutation_importance,
+            list_runs,
+            load_run,
+            compare_runs,
+            delete_run,
+            list_networks,
+            add_network,
+            remove_network,
+            select_network,
+            train_networks,
+            compare_networks,
+            search_hyperparameters
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
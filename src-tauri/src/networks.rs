@@ -0,0 +1,354 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    nn::{
+        any::AnyNetwork,
+        metrics::{EvaluationReport, Metric, MultiLabelReport, RegressionReport},
+        TaskType,
+    },
+    runs::align,
+    Settings,
+};
+
+// name of the network built by reset before any other was added
+pub const DEFAULT_NETWORK: &str = "default";
+
+/**
+ * Per epoch values recorded while a network trains.
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainingHistory {
+    pub mse: Vec<f64>,
+    pub mse_validation: Vec<f64>,
+    pub metrics: BTreeMap<Metric, Vec<f64>>,
+    // the MSE stopped being finite
+    pub diverged: bool,
+}
+
+// what one epoch of a network recorded
+pub struct EpochValues {
+    pub mse: f64,
+    pub mse_validation: f64,
+    pub metrics: Vec<(Metric, f64)>,
+}
+
+/**
+ * One network with the settings it was built from and what it learned so far.
+ */
+#[derive(Clone)]
+pub struct NamedNetwork {
+    pub nn: AnyNetwork,
+    // None for loaded models, which have no data
+    pub settings: Option<Settings>,
+    pub history: TrainingHistory,
+}
+
+/**
+ * The networks held by the backend, all built on the data of the last reset.
+ *
+ * the active one is shown by the client state and used by the single network commands.
+ */
+pub struct Networks {
+    networks: BTreeMap<String, NamedNetwork>,
+    active: String,
+}
+
+/**
+ * End of training results of one network, only the ones of its task type are filled.
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkEvaluation {
+    pub evaluation: Option<Vec<EvaluationReport>>,
+    pub multi_label_report: Option<MultiLabelReport>,
+    pub regression_report: Option<RegressionReport>,
+    pub cross_entropy_loss: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkSummary {
+    pub name: String,
+    pub active: bool,
+    // neurons of every hidden layer
+    pub topology: Vec<usize>,
+    pub epochs: usize,
+}
+
+/**
+ * Networks side by side, in name order, histories are padded with None to the longest one.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkComparison {
+    pub names: Vec<String>,
+    // [network][epoch]
+    pub mse_history: Vec<Vec<Option<f64>>>,
+    pub mse_validation_history: Vec<Vec<Option<f64>>>,
+    pub metric_history: BTreeMap<Metric, Vec<Vec<Option<f64>>>>,
+    pub evaluations: Vec<NetworkEvaluation>,
+    pub diverged: Vec<bool>,
+}
+
+impl TrainingHistory {
+    pub fn push(&mut self, mse: f64, mse_validation: f64, metrics: &[(Metric, f64)]) {
+        self.mse.push(mse);
+        self.mse_validation.push(mse_validation);
+        metrics.iter().for_each(|&(metric, value)| {
+            self.metrics.entry(metric).or_default().push(value);
+        });
+    }
+
+    // the values of the last epoch
+    pub fn last(&self) -> Option<EpochValues> {
+        Some(EpochValues {
+            mse: *self.mse.last()?,
+            mse_validation: *self.mse_validation.last()?,
+            metrics: self
+                .metrics
+                .iter()
+                .filter_map(|(&metric, values)| Some((metric, *values.last()?)))
+                .collect(),
+        })
+    }
+}
+
+impl NamedNetwork {
+    pub fn new(nn: AnyNetwork, settings: Option<Settings>) -> Self {
+        NamedNetwork {
+            nn,
+            settings,
+            history: TrainingHistory::default(),
+        }
+    }
+
+    /**
+     * Trains one epoch and records it in the history, None once the network diverged.
+     */
+    pub fn epoch(&mut self, tracked_metrics: &[Metric]) -> Option<EpochValues> {
+        let (mse, mse_validation) = self.nn.epoch();
+        if !mse.is_finite() {
+            self.history.diverged = true;
+            return None;
+        }
+        let metrics = tracked_metrics
+            .iter()
+            .filter_map(|&metric| self.nn.metric(metric).map(|value| (metric, value)))
+            .collect::<Vec<_>>();
+        self.history.push(mse, mse_validation, &metrics);
+        Some(EpochValues {
+            mse,
+            mse_validation,
+            metrics,
+        })
+    }
+}
+
+impl NetworkEvaluation {
+    pub fn new(nn: &AnyNetwork) -> Self {
+        match nn.task_type() {
+            TaskType::Classification => NetworkEvaluation {
+                evaluation: Some(nn.evaluation_reports()),
+                cross_entropy_loss: Some(nn.cross_entropy_loss()),
+                ..Default::default()
+            },
+            TaskType::MultiLabel => NetworkEvaluation {
+                multi_label_report: Some(nn.multi_label_report()),
+                cross_entropy_loss: Some(nn.cross_entropy_loss()),
+                ..Default::default()
+            },
+            TaskType::Regression => NetworkEvaluation {
+                regression_report: Some(nn.regression_report()),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Networks {
+    pub fn new(network: NamedNetwork) -> Self {
+        Networks {
+            networks: BTreeMap::from([(DEFAULT_NETWORK.to_string(), network)]),
+            active: DEFAULT_NETWORK.to_string(),
+        }
+    }
+
+    pub fn active(&self) -> &NamedNetwork {
+        &self.networks[&self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut NamedNetwork {
+        self.networks.get_mut(&self.active).unwrap()
+    }
+
+    pub fn active_name(&self) -> &str {
+        &self.active
+    }
+
+    pub fn get(&self, name: &str) -> Result<&NamedNetwork, String> {
+        self.networks
+            .get(name)
+            .ok_or(format!("No network named {}", name))
+    }
+
+    // adds or replaces a network
+    pub fn insert(&mut self, name: String, network: NamedNetwork) -> Result<(), String> {
+        if name.trim().is_empty() {
+            return Err("The network needs a name".to_string());
+        }
+        self.networks.insert(name, network);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), String> {
+        if name == self.active {
+            return Err("The active network cannot be removed".to_string());
+        }
+        self.networks
+            .remove(name)
+            .map(|_| ())
+            .ok_or(format!("No network named {}", name))
+    }
+
+    pub fn set_active(&mut self, name: &str) -> Result<(), String> {
+        self.get(name)?;
+        self.active = name.to_string();
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &NamedNetwork)> {
+        self.networks.iter()
+    }
+
+    pub fn summaries(&self) -> Vec<NetworkSummary> {
+        self.networks
+            .iter()
+            .map(|(name, network)| NetworkSummary {
+                name: name.clone(),
+                active: *name == self.active,
+                topology: network.nn.hidden_layers_topology(),
+                epochs: network.history.mse.len(),
+            })
+            .collect()
+    }
+
+    /**
+     * Trains one more epoch of every network that has not diverged or reached desired_mse, in parallel.
+     *
+     * Returns whether any network trained this epoch.
+     */
+    pub fn epoch(&mut self, tracked_metrics: &[Metric], desired_mse: f64) -> bool {
+        self.networks
+            .par_iter_mut()
+            .filter(|(_, network)| {
+                !network.history.diverged
+                    && network
+                        .history
+                        .mse
+                        .last()
+                        .is_none_or(|&mse| mse > desired_mse)
+            })
+            .map(|(_, network)| network.epoch(tracked_metrics).is_some())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .any(|trained| trained)
+    }
+
+    pub fn comparison(&self) -> NetworkComparison {
+        let histories = self
+            .networks
+            .values()
+            .map(|network| &network.history)
+            .collect::<Vec<_>>();
+        let metrics = histories
+            .iter()
+            .flat_map(|history| history.metrics.keys().copied())
+            .collect::<BTreeSet<_>>();
+        NetworkComparison {
+            names: self.networks.keys().cloned().collect(),
+            mse_history: align(&histories.iter().map(|h| &h.mse[..]).collect::<Vec<_>>()),
+            mse_validation_history: align(
+                &histories
+                    .iter()
+                    .map(|h| &h.mse_validation[..])
+                    .collect::<Vec<_>>(),
+            ),
+            metric_history: metrics
+                .into_iter()
+                .map(|metric| {
+                    let values = histories
+                        .iter()
+                        .map(|h| h.metrics.get(&metric).map_or(&[][..], |v| &v[..]))
+                        .collect::<Vec<_>>();
+                    (metric, align(&values))
+                })
+                .collect(),
+            evaluations: self
+                .networks
+                .values()
+                .map(|network| NetworkEvaluation::new(&network.nn))
+                .collect(),
+            diverged: histories.iter().map(|h| h.diverged).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nn::{activation_functions::ActivationFunction, NeuralNetwork};
+
+    fn network(topology: Vec<usize>, data: &[(Vec<f64>, Vec<f64>)]) -> NamedNetwork {
+        let activations = vec![ActivationFunction::Tanh; topology.len()];
+        NamedNetwork::new(
+            NeuralNetwork::<f64>::with_seed(
+                Some(1),
+                2,
+                topology,
+                &activations,
+                2,
+                ActivationFunction::Softmax,
+                0.1,
+                data.to_vec(),
+                data.to_vec(),
+                vec![],
+            )
+            .into(),
+            None,
+        )
+    }
+
+    #[test]
+    fn networks_train_in_lockstep() {
+        let data = (0..20)
+            .map(|i| {
+                let class = (i % 2) as f64;
+                (vec![i as f64, class * 10.0], vec![1.0 - class, class])
+            })
+            .collect::<Vec<_>>();
+        let mut networks = Networks::new(network(vec![4], &data));
+        networks
+            .insert("wide".to_string(), network(vec![8, 4], &data))
+            .unwrap();
+        assert!(networks.remove(DEFAULT_NETWORK).is_err());
+
+        (0..3).for_each(|_| {
+            networks.epoch(&[Metric::TrainingAccuracy], 0.0);
+        });
+        let comparison = networks.comparison();
+        assert_eq!(comparison.names, vec![DEFAULT_NETWORK, "wide"]);
+        assert!(comparison.mse_history.iter().all(|h| h.len() == 3));
+        assert_eq!(
+            comparison.metric_history[&Metric::TrainingAccuracy].len(),
+            2
+        );
+        assert!(comparison
+            .evaluations
+            .iter()
+            .all(|e| e.evaluation.is_some()));
+    }
+}
@@ -1,6 +1,12 @@
 // use std::f64::consts::E;
 
-pub type ActivationFunctionWrapper = Box<&'static dyn ActivationFunctionTrait>;
+use super::{expression::Expression, float::Float};
+
+/**
+ * Activation functions are owned by each neuron so they can carry
+ * (possibly learnable) parameters such as the PReLU slope.
+ */
+pub type ActivationFunctionWrapper<T = f64> = Box<dyn ActivationFunctionTrait<T>>;
 
 // impl std::fmt::Debug for ActivationFunctionWrapper {
 //     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -8,153 +14,339 @@ pub type ActivationFunctionWrapper = Box<&'static dyn ActivationFunctionTrait>;
 //     }
 // }
 
-#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
+impl<T: Float> Clone for ActivationFunctionWrapper<T> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum ActivationFunction {
     Linear,
     Sigmoid,
     Relu,
     LeakyRelu,
+    Prelu,
     Tanh,
     Softmax,
+    Elu,
+    Selu,
+    Gelu,
+    #[serde(alias = "silu")]
+    Swish,
+    Mish,
+    Softplus,
+    Softsign,
+    HardSigmoid,
+    HardTanh,
+    Gaussian,
+    // user defined math expression of x, e.g. "x / (1 + abs(x))"
+    Custom(String),
+}
+
+/**
+ * Whether every neuron of a PReLU layer learns its own slope
+ * or all neurons share (and update) a single one.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PreluSharing {
+    PerNeuron,
+    PerLayer,
+}
+
+/**
+ * Parameters of the parametric activation functions.
+ *
+ * Only the fields used by the selected activation function are read.
+ */
+#[derive(Debug, Copy, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ActivationParameters {
+    pub leaky_relu_slope: f64,
+    pub elu_alpha: f64,
+    pub swish_beta: f64,
+    // initial value, learned during backpropagation
+    pub prelu_slope: f64,
+    pub prelu_sharing: PreluSharing,
 }
 
-pub fn get_activation_function(
-    activation_function: ActivationFunction,
-) -> ActivationFunctionWrapper {
+impl Default for ActivationParameters {
+    fn default() -> Self {
+        ActivationParameters {
+            leaky_relu_slope: 0.01,
+            elu_alpha: 1.,
+            swish_beta: 1.,
+            prelu_slope: 0.25,
+            prelu_sharing: PreluSharing::PerNeuron,
+        }
+    }
+}
+
+pub fn get_activation_function<T: Float>(
+    activation_function: &ActivationFunction,
+    parameters: &ActivationParameters,
+) -> ActivationFunctionWrapper<T> {
     match activation_function {
-        ActivationFunction::Linear => Box::new(&Linear {}),
-        ActivationFunction::Sigmoid => Box::new(&Sigmoid {}),
-        ActivationFunction::Relu => Box::new(&Relu {}),
-        ActivationFunction::LeakyRelu => Box::new(&LeakyRelu {}),
-        ActivationFunction::Tanh => Box::new(&Tanh {}),
-        ActivationFunction::Softmax => Box::new(&Softmax {}),
+        ActivationFunction::Linear => Box::new(Linear),
+        ActivationFunction::Sigmoid => Box::new(Sigmoid),
+        ActivationFunction::Relu => Box::new(Relu),
+        ActivationFunction::LeakyRelu => Box::new(LeakyRelu {
+            slope: T::from_f64(parameters.leaky_relu_slope),
+        }),
+        ActivationFunction::Prelu => Box::new(Prelu {
+            slope: T::from_f64(parameters.prelu_slope),
+        }),
+        ActivationFunction::Tanh => Box::new(Tanh),
+        ActivationFunction::Softmax => Box::new(Softmax),
+        ActivationFunction::Elu => Box::new(Elu {
+            alpha: T::from_f64(parameters.elu_alpha),
+        }),
+        ActivationFunction::Selu => Box::new(Selu),
+        ActivationFunction::Gelu => Box::new(Gelu),
+        ActivationFunction::Swish => Box::new(Swish {
+            beta: T::from_f64(parameters.swish_beta),
+        }),
+        ActivationFunction::Mish => Box::new(Mish),
+        ActivationFunction::Softplus => Box::new(Softplus),
+        ActivationFunction::Softsign => Box::new(Softsign),
+        ActivationFunction::HardSigmoid => Box::new(HardSigmoid),
+        ActivationFunction::HardTanh => Box::new(HardTanh),
+        ActivationFunction::Gaussian => Box::new(Gaussian),
+        ActivationFunction::Custom(source) => Box::new(Custom::new(source)),
     }
 }
 
-pub trait ActivationFunctionTrait
+// range sampled when checking that a custom function is usable
+const CUSTOM_CHECK_RANGE: (f64, f64) = (-10., 10.);
+
+/**
+ * Checks that a custom activation function parses and that both the function
+ * and its derivative are finite over CUSTOM_CHECK_RANGE.
+ *
+ * Built-in functions are always valid.
+ */
+pub fn validate_activation_function(
+    activation_function: &ActivationFunction,
+) -> Result<(), String> {
+    let ActivationFunction::Custom(source) = activation_function else {
+        return Ok(());
+    };
+    let function = Expression::parse(source).map_err(|e| format!("`{}`: {}", source, e))?;
+    let derivative = function.derivative();
+    let (from, to) = CUSTOM_CHECK_RANGE;
+    let steps = 200;
+    (0..=steps)
+        .map(|i| from + (to - from) * i as f64 / steps as f64)
+        .try_for_each(|x| {
+            if !function.evaluate(x).is_finite() {
+                Err(format!("`{}` is not finite at x = {}", source, x))
+            } else if !derivative.evaluate(x).is_finite() {
+                Err(format!(
+                    "the derivative of `{}` is not finite at x = {}",
+                    source, x
+                ))
+            } else {
+                Ok(())
+            }
+        })
+}
+
+pub trait ActivationFunctionTrait<T: Float>
+where
+    Self: ActivationFunctionClone<T> + std::fmt::Debug + Send + Sync,
+{
+    fn apply(&self, big_x: T) -> T;
+    fn commit(&self, big_x: T, all_outputs: &[T]) -> T;
+    fn derivative(&self, big_x: T, all_outputs: &[T]) -> T;
+
+    // learnable parameter of the function (e.g. PReLU slope), if any
+    fn parameter(&self) -> Option<T> {
+        None
+    }
+
+    // partial derivative of the output with respect to the learnable parameter
+    fn parameter_derivative(&self, _big_x: T) -> T {
+        T::zero()
+    }
+
+    fn set_parameter(&mut self, _value: T) {}
+}
+
+pub trait ActivationFunctionClone<T> {
+    fn clone_box(&self) -> ActivationFunctionWrapper<T>;
+}
+
+impl<T, F> ActivationFunctionClone<T> for F
 where
-    Self: std::fmt::Debug + Send + Sync,
+    T: Float,
+    F: 'static + ActivationFunctionTrait<T> + Clone,
 {
-    fn apply(&self, big_x: f64) -> f64;
-    fn commit(&self, big_x: f64, all_outputs: &Vec<f64>) -> f64;
-    fn derivative(&self, big_x: f64, all_outputs: &Vec<f64>) -> f64;
+    fn clone_box(&self) -> ActivationFunctionWrapper<T> {
+        Box::new(self.clone())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Linear;
-impl ActivationFunctionTrait for Linear {
-    fn apply(&self, x: f64) -> f64 {
+impl<T: Float> ActivationFunctionTrait<T> for Linear {
+    fn apply(&self, x: T) -> T {
         x
     }
 
-    fn commit(&self, x: f64, _all_outputs: &Vec<f64>) -> f64 {
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
         self.apply(x)
     }
 
-    fn derivative(&self, _x: f64, _all_outputs: &Vec<f64>) -> f64 {
-        return 1.;
+    fn derivative(&self, _x: T, _all_outputs: &[T]) -> T {
+        return T::one();
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Sigmoid;
-impl ActivationFunctionTrait for Sigmoid {
-    fn apply(&self, x: f64) -> f64 {
+impl<T: Float> ActivationFunctionTrait<T> for Sigmoid {
+    fn apply(&self, x: T) -> T {
         // 1.0 / (1.0 + E.powf(-1.0 * x));
-        1. / (1. + (-1. * x).exp())
+        T::one() / (T::one() + (-x).exp())
     }
 
-    fn commit(&self, x: f64, _all_outputs: &Vec<f64>) -> f64 {
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
         self.apply(x)
     }
 
-    fn derivative(&self, x: f64, _all_outputs: &Vec<f64>) -> f64 {
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
         let v = self.apply(x);
-        v * (1. - v)
+        v * (T::one() - v)
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Relu;
-impl ActivationFunctionTrait for Relu {
-    fn apply(&self, x: f64) -> f64 {
-        f64::max(0., x)
+impl<T: Float> ActivationFunctionTrait<T> for Relu {
+    fn apply(&self, x: T) -> T {
+        x.max(T::zero())
     }
 
-    fn commit(&self, x: f64, _all_outputs: &Vec<f64>) -> f64 {
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
         self.apply(x)
     }
 
-    fn derivative(&self, x: f64, _all_outputs: &Vec<f64>) -> f64 {
-        if x < 0. {
-            0.
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        if x < T::zero() {
+            T::zero()
         } else {
-            1.
+            T::one()
         }
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct LeakyRelu;
-impl ActivationFunctionTrait for LeakyRelu {
-    fn apply(&self, x: f64) -> f64 {
-        if x < 0. {
-            0.01 * x
+pub struct LeakyRelu<T> {
+    slope: T,
+}
+impl<T: Float> ActivationFunctionTrait<T> for LeakyRelu<T> {
+    fn apply(&self, x: T) -> T {
+        if x < T::zero() {
+            self.slope * x
         } else {
             x
         }
     }
 
-    fn commit(&self, x: f64, _all_outputs: &Vec<f64>) -> f64 {
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
         self.apply(x)
     }
 
-    fn derivative(&self, x: f64, _all_outputs: &Vec<f64>) -> f64 {
-        if x < 0. {
-            0.01
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        if x < T::zero() {
+            self.slope
+        } else {
+            T::one()
+        }
+    }
+}
+
+// leaky relu whose slope is learned by backpropagation
+#[derive(Debug, Clone)]
+pub struct Prelu<T> {
+    slope: T,
+}
+impl<T: Float> ActivationFunctionTrait<T> for Prelu<T> {
+    fn apply(&self, x: T) -> T {
+        if x < T::zero() {
+            self.slope * x
         } else {
-            1.
+            x
         }
     }
+
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
+        self.apply(x)
+    }
+
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        if x < T::zero() {
+            self.slope
+        } else {
+            T::one()
+        }
+    }
+
+    fn parameter(&self) -> Option<T> {
+        Some(self.slope)
+    }
+
+    fn parameter_derivative(&self, x: T) -> T {
+        if x < T::zero() {
+            x
+        } else {
+            T::zero()
+        }
+    }
+
+    fn set_parameter(&mut self, value: T) {
+        self.slope = value;
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Tanh;
-impl ActivationFunctionTrait for Tanh {
-    fn apply(&self, x: f64) -> f64 {
+impl<T: Float> ActivationFunctionTrait<T> for Tanh {
+    fn apply(&self, x: T) -> T {
         x.tanh()
         // 2. / (1. + (-2. * x).exp()) - 1.
     }
 
-    fn commit(&self, x: f64, _all_outputs: &Vec<f64>) -> f64 {
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
         self.apply(x)
     }
 
-    fn derivative(&self, x: f64, _all_outputs: &Vec<f64>) -> f64 {
-        1. - x.tanh().powi(2)
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        T::one() - x.tanh().powi(2)
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct Softmax;
-impl ActivationFunctionTrait for Softmax {
-    fn apply(&self, x: f64) -> f64 {
+impl<T: Float> ActivationFunctionTrait<T> for Softmax {
+    fn apply(&self, x: T) -> T {
         x
     }
 
-    fn commit(&self, x: f64, all_outputs: &Vec<f64>) -> f64 {
-        let max_val = all_outputs.iter().cloned().reduce(f64::max).unwrap();
+    fn commit(&self, x: T, all_outputs: &[T]) -> T {
+        let max_val = all_outputs.iter().cloned().reduce(T::max).unwrap();
         // let max = all_outputs
         //     .iter()
         //     .fold(f64::MIN, |acc, &i| if acc > i { acc } else { i });
-        (x - max_val).exp() / all_outputs.iter().map(|i| (i - max_val).exp()).sum::<f64>()
+        (x - max_val).exp() / all_outputs.iter().map(|&i| (i - max_val).exp()).sum::<T>()
         // x.exp() / all_outputs.iter().map(|i| i.exp()).sum::<f64>()
     }
 
-    fn derivative(&self, x: f64, all_outputs: &Vec<f64>) -> f64 {
+    fn derivative(&self, x: T, all_outputs: &[T]) -> T {
         let y = self.commit(x, all_outputs);
-        return y * (1. - y);
+        return y * (T::one() - y);
         // let index = all_outputs.iter().position(|&r| r == x).unwrap();
         // let all_y = all_outputs
         //     .iter()
@@ -185,3 +377,398 @@ impl ActivationFunctionTrait for Softmax {
         // sum
     }
 }
+
+/**
+ * softmax of a whole layer at once, O(n) instead of calling commit for every output.
+ */
+pub fn softmax_in_place<T: Float>(values: &mut [T]) {
+    let max_val = values.iter().cloned().fold(T::neg_infinity(), T::max);
+    let mut sum = T::zero();
+    values.iter_mut().for_each(|v| {
+        *v = (*v - max_val).exp();
+        sum += *v;
+    });
+    values.iter_mut().for_each(|v| *v /= sum);
+}
+
+#[derive(Debug, Clone)]
+pub struct Elu<T> {
+    alpha: T,
+}
+impl<T: Float> ActivationFunctionTrait<T> for Elu<T> {
+    fn apply(&self, x: T) -> T {
+        if x > T::zero() {
+            x
+        } else {
+            self.alpha * x.exp_m1()
+        }
+    }
+
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
+        self.apply(x)
+    }
+
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        if x > T::zero() {
+            T::one()
+        } else {
+            self.alpha * x.exp()
+        }
+    }
+}
+
+// self-normalizing constants from Klambauer et al. (2017)
+const SELU_LAMBDA: f64 = 1.050_700_987_355_480_5;
+const SELU_ALPHA: f64 = 1.673_263_242_354_377_2;
+
+#[derive(Debug, Clone)]
+pub struct Selu;
+impl<T: Float> ActivationFunctionTrait<T> for Selu {
+    fn apply(&self, x: T) -> T {
+        let lambda = T::from_f64(SELU_LAMBDA);
+        if x > T::zero() {
+            lambda * x
+        } else {
+            lambda * T::from_f64(SELU_ALPHA) * x.exp_m1()
+        }
+    }
+
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
+        self.apply(x)
+    }
+
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        let lambda = T::from_f64(SELU_LAMBDA);
+        if x > T::zero() {
+            lambda
+        } else {
+            lambda * T::from_f64(SELU_ALPHA) * x.exp()
+        }
+    }
+}
+
+// sqrt(2 / PI) used by the tanh approximation of GELU
+const GELU_C: f64 = 0.797_884_560_802_865_4;
+
+#[derive(Debug, Clone)]
+pub struct Gelu;
+impl<T: Float> ActivationFunctionTrait<T> for Gelu {
+    fn apply(&self, x: T) -> T {
+        let (c, k, half) = (T::from_f64(GELU_C), T::from_f64(0.044715), T::from_f64(0.5));
+        half * x * (T::one() + (c * (x + k * x.powi(3))).tanh())
+    }
+
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
+        self.apply(x)
+    }
+
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        let (c, k, half) = (T::from_f64(GELU_C), T::from_f64(0.044715), T::from_f64(0.5));
+        let t = (c * (x + k * x.powi(3))).tanh();
+        half * (T::one() + t)
+            + half * x * (T::one() - t * t) * c * (T::one() + T::from_f64(3.) * k * x * x)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Swish<T> {
+    beta: T,
+}
+impl<T: Float> ActivationFunctionTrait<T> for Swish<T> {
+    fn apply(&self, x: T) -> T {
+        x * Sigmoid.apply(self.beta * x)
+    }
+
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
+        self.apply(x)
+    }
+
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        let s = Sigmoid.apply(self.beta * x);
+        s + self.beta * x * s * (T::one() - s)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Mish;
+impl<T: Float> ActivationFunctionTrait<T> for Mish {
+    fn apply(&self, x: T) -> T {
+        x * Softplus.apply(x).tanh()
+    }
+
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
+        self.apply(x)
+    }
+
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        let t = Softplus.apply(x).tanh();
+        t + x * (T::one() - t * t) * Sigmoid.apply(x)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Softplus;
+impl<T: Float> ActivationFunctionTrait<T> for Softplus {
+    fn apply(&self, x: T) -> T {
+        // ln(1 + e^x) without overflowing for large x
+        x.max(T::zero()) + (-x.abs()).exp().ln_1p()
+    }
+
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
+        self.apply(x)
+    }
+
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        Sigmoid.apply(x)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Softsign;
+impl<T: Float> ActivationFunctionTrait<T> for Softsign {
+    fn apply(&self, x: T) -> T {
+        x / (T::one() + x.abs())
+    }
+
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
+        self.apply(x)
+    }
+
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        T::one() / (T::one() + x.abs()).powi(2)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HardSigmoid;
+impl<T: Float> ActivationFunctionTrait<T> for HardSigmoid {
+    fn apply(&self, x: T) -> T {
+        (T::from_f64(0.2) * x + T::from_f64(0.5))
+            .max(T::zero())
+            .min(T::one())
+    }
+
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
+        self.apply(x)
+    }
+
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        if x > T::from_f64(-2.5) && x < T::from_f64(2.5) {
+            T::from_f64(0.2)
+        } else {
+            T::zero()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HardTanh;
+impl<T: Float> ActivationFunctionTrait<T> for HardTanh {
+    fn apply(&self, x: T) -> T {
+        x.max(-T::one()).min(T::one())
+    }
+
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
+        self.apply(x)
+    }
+
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        if x > -T::one() && x < T::one() {
+            T::one()
+        } else {
+            T::zero()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Gaussian;
+impl<T: Float> ActivationFunctionTrait<T> for Gaussian {
+    fn apply(&self, x: T) -> T {
+        (-x * x).exp()
+    }
+
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
+        self.apply(x)
+    }
+
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        T::from_f64(-2.) * x * (-x * x).exp()
+    }
+}
+
+// parsed user expression, the derivative comes from symbolic differentiation
+#[derive(Debug, Clone)]
+pub struct Custom {
+    function: Expression,
+    derivative: Expression,
+}
+impl Custom {
+    fn new(source: &str) -> Self {
+        // invalid expressions are rejected by validate_activation_function,
+        // fall back to NaN so training reports non-finite values
+        let function = Expression::parse(source).unwrap_or(Expression::Number(f64::NAN));
+        let derivative = function.derivative();
+        Custom {
+            function,
+            derivative,
+        }
+    }
+}
+// expressions are evaluated in f64 whatever the precision of the network
+impl<T: Float> ActivationFunctionTrait<T> for Custom {
+    fn apply(&self, x: T) -> T {
+        T::from_f64(self.function.evaluate(x.as_f64()))
+    }
+
+    fn commit(&self, x: T, _all_outputs: &[T]) -> T {
+        self.apply(x)
+    }
+
+    fn derivative(&self, x: T, _all_outputs: &[T]) -> T {
+        T::from_f64(self.derivative.evaluate(x.as_f64()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // points chosen away from the kinks of the piecewise functions
+    const POINTS: [f64; 8] = [-3.7, -1.3, -0.4, -0.05, 0.3, 0.9, 2.1, 4.2];
+
+    fn assert_derivative(activation_function: ActivationFunction) {
+        let parameters = ActivationParameters {
+            leaky_relu_slope: 0.2,
+            elu_alpha: 1.5,
+            swish_beta: 0.7,
+            ..Default::default()
+        };
+        let f = get_activation_function::<f64>(&activation_function, &parameters);
+        let h = 1e-6;
+        for x in POINTS {
+            let numeric = (f.apply(x + h) - f.apply(x - h)) / (2. * h);
+            let analytic = f.derivative(x, &[]);
+            assert!(
+                (numeric - analytic).abs() < 1e-6,
+                "{:?} at {}: numeric {} != analytic {}",
+                activation_function,
+                x,
+                numeric,
+                analytic
+            );
+        }
+    }
+
+    #[test]
+    fn elu_derivative() {
+        assert_derivative(ActivationFunction::Elu);
+    }
+
+    #[test]
+    fn selu_derivative() {
+        assert_derivative(ActivationFunction::Selu);
+    }
+
+    #[test]
+    fn gelu_derivative() {
+        assert_derivative(ActivationFunction::Gelu);
+    }
+
+    #[test]
+    fn swish_derivative() {
+        assert_derivative(ActivationFunction::Swish);
+    }
+
+    #[test]
+    fn mish_derivative() {
+        assert_derivative(ActivationFunction::Mish);
+    }
+
+    #[test]
+    fn softplus_derivative() {
+        assert_derivative(ActivationFunction::Softplus);
+    }
+
+    #[test]
+    fn softsign_derivative() {
+        assert_derivative(ActivationFunction::Softsign);
+    }
+
+    #[test]
+    fn hard_sigmoid_derivative() {
+        assert_derivative(ActivationFunction::HardSigmoid);
+    }
+
+    #[test]
+    fn hard_tanh_derivative() {
+        assert_derivative(ActivationFunction::HardTanh);
+    }
+
+    #[test]
+    fn gaussian_derivative() {
+        assert_derivative(ActivationFunction::Gaussian);
+    }
+
+    #[test]
+    fn leaky_relu_derivative() {
+        assert_derivative(ActivationFunction::LeakyRelu);
+    }
+
+    #[test]
+    fn prelu_derivative() {
+        assert_derivative(ActivationFunction::Prelu);
+    }
+
+    #[test]
+    fn prelu_parameter_derivative() {
+        let mut f = get_activation_function::<f64>(
+            &ActivationFunction::Prelu,
+            &ActivationParameters::default(),
+        );
+        let h = 1e-6;
+        for x in POINTS {
+            let slope = f.parameter().unwrap();
+            f.set_parameter(slope + h);
+            let above = f.apply(x);
+            f.set_parameter(slope - h);
+            let below = f.apply(x);
+            f.set_parameter(slope);
+            let numeric = (above - below) / (2. * h);
+            assert!((numeric - f.parameter_derivative(x)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn custom_derivative() {
+        assert_derivative(ActivationFunction::Custom("x / (1 + abs(x))".to_string()));
+    }
+
+    #[test]
+    fn custom_validation() {
+        let custom = |s: &str| ActivationFunction::Custom(s.to_string());
+        assert!(validate_activation_function(&custom("x * tanh(x)")).is_ok());
+        assert!(validate_activation_function(&custom("x / (1 + ")).is_err());
+        assert!(validate_activation_function(&custom("ln(x)")).is_err());
+        assert!(validate_activation_function(&custom("1 / x")).is_err());
+    }
+
+    #[test]
+    fn serde_names() {
+        let parsed: Vec<ActivationFunction> =
+            serde_json::from_str(r#"["hardSigmoid", "silu", "swish", "gelu"]"#).unwrap();
+        assert!(matches!(
+            parsed.as_slice(),
+            [
+                ActivationFunction::HardSigmoid,
+                ActivationFunction::Swish,
+                ActivationFunction::Swish,
+                ActivationFunction::Gelu
+            ]
+        ));
+        assert_eq!(
+            serde_json::to_string(&ActivationFunction::HardTanh).unwrap(),
+            r#""hardTanh""#
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use super::{
+    calibration::{CalibrationMethod, CalibrationReport},
+    curves::CurveReport,
+    explain::{AttributionSettings, Attributions, PermutationImportance, Saliency},
+    float::Precision,
+    layer::LayerType,
+    matrix::Matrix,
+    metrics::{EvaluationReport, Metric, MultiLabelReport, RegressionReport},
+    model::Model,
+    stats::{NetworkStats, StatsThresholds},
+    NeuralNetwork, Split, TaskType,
+};
+
+/**
+ * A network in the precision picked at runtime, by the settings or by a loaded model.
+ *
+ * forwards the methods the app needs, their results are f64 whatever the precision.
+ */
+#[derive(Clone, Debug)]
+pub enum AnyNetwork {
+    F32(NeuralNetwork<f32>),
+    F64(NeuralNetwork<f64>),
+}
+
+// runs the same expression on the network of either precision
+macro_rules! dispatch {
+    ($network:expr, $nn:ident => $body:expr) => {
+        match $network {
+            AnyNetwork::F32($nn) => $body,
+            AnyNetwork::F64($nn) => $body,
+        }
+    };
+}
+
+impl From<NeuralNetwork<f32>> for AnyNetwork {
+    fn from(nn: NeuralNetwork<f32>) -> Self {
+        AnyNetwork::F32(nn)
+    }
+}
+
+impl From<NeuralNetwork<f64>> for AnyNetwork {
+    fn from(nn: NeuralNetwork<f64>) -> Self {
+        AnyNetwork::F64(nn)
+    }
+}
+
+#[allow(dead_code)]
+impl AnyNetwork {
+    /**
+     * Rebuilds a saved model in the precision it was trained with.
+     */
+    pub fn from_model(model: &Model) -> Result<Self, String> {
+        Ok(match model.precision {
+            Precision::F32 => AnyNetwork::F32(NeuralNetwork::from_model(model)?),
+            Precision::F64 => AnyNetwork::F64(NeuralNetwork::from_model(model)?),
+        })
+    }
+
+    /**
+     * Replaces the weights and calibration with the ones of a saved model, keeping the data.
+     */
+    pub fn load_parameters(&mut self, model: &Model) -> Result<(), String> {
+        dispatch!(self, nn => {
+            let trained = NeuralNetwork::from_model(model)?;
+            nn.layers = trained.layers;
+            nn.calibration = trained.calibration;
+        });
+        Ok(())
+    }
+
+    pub fn to_model(&self) -> Model {
+        dispatch!(self, nn => nn.to_model())
+    }
+
+    pub fn precision(&self) -> Precision {
+        dispatch!(self, nn => nn.precision())
+    }
+
+    pub fn task_type(&self) -> TaskType {
+        dispatch!(self, nn => nn.task_type)
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        dispatch!(self, nn => nn.seed)
+    }
+
+    pub fn normalization_factors(&self) -> &[(f64, f64)] {
+        dispatch!(self, nn => &nn.normalization_factors)
+    }
+
+    // neurons of every hidden layer
+    pub fn hidden_layers_topology(&self) -> Vec<usize> {
+        dispatch!(self, nn => nn.layers[..nn.layers.len() - 1]
+            .iter()
+            .map(|layer| layer.neurons_count())
+            .collect())
+    }
+
+    pub fn epoch(&mut self) -> (f64, f64) {
+        dispatch!(self, nn => nn.epoch())
+    }
+
+    pub fn metric(&self, metric: Metric) -> Option<f64> {
+        dispatch!(self, nn => nn.metric(metric))
+    }
+
+    pub fn predict(&self, inputs: &[f64]) -> Vec<f64> {
+        dispatch!(self, nn => nn.predict(&inputs.to_vec()))
+    }
+
+    pub fn predict_many<R: AsRef<[f64]> + Sync>(&self, inputs: &[R]) -> Matrix<f64> {
+        dispatch!(self, nn => nn.predict_many(inputs))
+    }
+
+    pub fn hidden_outputs_many<R: AsRef<[f64]> + Sync>(&self, inputs: &[R]) -> Vec<Matrix<f64>> {
+        dispatch!(self, nn => nn.hidden_outputs_many(inputs))
+    }
+
+    pub fn calibrate(&mut self, method: CalibrationMethod) -> Result<(), String> {
+        dispatch!(self, nn => nn.calibrate(method))
+    }
+
+    pub fn calibration_report(&self, split: Split, bins_count: usize) -> CalibrationReport {
+        dispatch!(self, nn => nn.calibration_report(split, bins_count))
+    }
+
+    pub fn confusion_matrix(&self) -> Vec<Vec<usize>> {
+        dispatch!(self, nn => nn.confusion_matrix())
+    }
+
+    pub fn evaluation_reports(&self) -> Vec<EvaluationReport> {
+        dispatch!(self, nn => nn.evaluation_reports())
+    }
+
+    pub fn cross_entropy_loss(&self) -> f64 {
+        dispatch!(self, nn => nn.cross_entropy_loss())
+    }
+
+    pub fn curve_reports(&self) -> Vec<CurveReport> {
+        dispatch!(self, nn => nn.curve_reports())
+    }
+
+    pub fn multi_label_report(&self) -> MultiLabelReport {
+        dispatch!(self, nn => nn.multi_label_report())
+    }
+
+    pub fn regression_report(&self) -> RegressionReport {
+        dispatch!(self, nn => nn.regression_report())
+    }
+
+    pub fn get_parameters(&self) -> Vec<Vec<(Vec<f64>, f64, LayerType)>> {
+        dispatch!(self, nn => nn.get_parameters())
+    }
+
+    pub fn layer_stats(&self, thresholds: &StatsThresholds) -> NetworkStats {
+        dispatch!(self, nn => nn.layer_stats(thresholds))
+    }
+
+    pub fn saliency(&self, inputs: &[f64]) -> Result<Saliency, String> {
+        dispatch!(self, nn => nn.saliency(inputs))
+    }
+
+    pub fn permutation_importance(
+        &self,
+        split: Split,
+        repeats: usize,
+    ) -> Result<PermutationImportance, String> {
+        dispatch!(self, nn => nn.permutation_importance(split, repeats))
+    }
+
+    pub fn attributions(
+        &self,
+        inputs: &[f64],
+        settings: &AttributionSettings,
+    ) -> Result<Attributions, String> {
+        dispatch!(self, nn => nn.attributions(inputs, settings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::activation_functions::ActivationFunction;
+    use super::*;
+
+    #[test]
+    fn loaded_models_keep_their_precision() {
+        let data = (0..40)
+            .map(|i| (vec![i as f64, (i * 7 % 11) as f64], vec![(i % 2) as f64]))
+            .collect::<Vec<_>>();
+        let mut nn = AnyNetwork::from(NeuralNetwork::<f32>::with_seed(
+            Some(1),
+            2,
+            vec![4],
+            &[ActivationFunction::Tanh],
+            1,
+            ActivationFunction::Sigmoid,
+            0.1,
+            data.clone(),
+            vec![],
+            vec![],
+        ));
+        nn.epoch();
+
+        let loaded = AnyNetwork::from_model(&nn.to_model()).unwrap();
+        assert_eq!(loaded.precision(), Precision::F32);
+        assert_eq!(loaded.hidden_layers_topology(), vec![4]);
+        data.iter().for_each(|(inputs, _)| {
+            assert_eq!(nn.predict(inputs), loaded.predict(inputs));
+        });
+    }
+}
@@ -0,0 +1,313 @@
+use serde::{Deserialize, Serialize};
+
+use super::activation_functions::softmax_in_place;
+use super::Split;
+
+/**
+ * Post-hoc calibration applied to the output layer logits.
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Calibration {
+    #[default]
+    None,
+    // softmax(z / T) or sigmoid(z / T)
+    Temperature(f64),
+    // sigmoid(a * z + b) per class (a, b)
+    Platt(Vec<(f64, f64)>),
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CalibrationMethod {
+    #[default]
+    None,
+    Temperature,
+    Platt,
+}
+
+/**
+ * Expected calibration error and reliability diagram of a split.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibrationReport {
+    pub split: Split,
+    pub calibration: Calibration,
+    pub expected_calibration_error: f64,
+    pub bins: Vec<ReliabilityBin>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReliabilityBin {
+    pub lower: f64,
+    pub upper: f64,
+    // mean predicted probability of the samples in this bin
+    pub confidence: f64,
+    // fraction of those samples that were correct
+    pub accuracy: f64,
+    pub count: usize,
+}
+
+impl CalibrationReport {
+    /**
+     * samples are (predicted probability, prediction was correct) pairs.
+     */
+    pub fn new(
+        split: Split,
+        calibration: Calibration,
+        samples: &[(f64, bool)],
+        bins_count: usize,
+    ) -> Self {
+        let mut bins = (0..bins_count)
+            .map(|i| ReliabilityBin {
+                lower: i as f64 / bins_count as f64,
+                upper: (i + 1) as f64 / bins_count as f64,
+                confidence: 0.0,
+                accuracy: 0.0,
+                count: 0,
+            })
+            .collect::<Vec<_>>();
+
+        samples.iter().for_each(|&(p, correct)| {
+            let i = ((p * bins_count as f64) as usize).min(bins_count - 1);
+            bins[i].confidence += p;
+            bins[i].accuracy += correct as usize as f64;
+            bins[i].count += 1;
+        });
+
+        let n = samples.len().max(1) as f64;
+        let mut expected_calibration_error = 0.0;
+        bins.iter_mut().filter(|b| b.count > 0).for_each(|b| {
+            b.confidence /= b.count as f64;
+            b.accuracy /= b.count as f64;
+            expected_calibration_error += b.count as f64 / n * (b.accuracy - b.confidence).abs();
+        });
+
+        CalibrationReport {
+            split,
+            calibration,
+            expected_calibration_error,
+            bins,
+        }
+    }
+}
+
+impl Calibration {
+    /**
+     * Turns output logits into calibrated probabilities.
+     *
+     * exclusive (softmax) outputs are normalized to sum to one, otherwise every output is
+     * independent.
+     */
+    pub fn apply(&self, logits: &[f64], exclusive: bool) -> Vec<f64> {
+        let mut probabilities = logits.to_vec();
+        self.apply_in_place(&mut probabilities, exclusive);
+        probabilities
+    }
+
+    // same as apply, overwriting the logits with the probabilities
+    pub fn apply_in_place(&self, values: &mut [f64], exclusive: bool) {
+        match self {
+            Calibration::None => {}
+            Calibration::Temperature(t) => {
+                values.iter_mut().for_each(|z| *z /= t);
+                if exclusive {
+                    softmax_in_place(values);
+                } else {
+                    values.iter_mut().for_each(|z| *z = sigmoid(*z));
+                }
+            }
+            Calibration::Platt(parameters) => {
+                values
+                    .iter_mut()
+                    .zip(parameters)
+                    .for_each(|(z, (a, b))| *z = sigmoid(a * *z + b));
+                if exclusive {
+                    let sum = values.iter().sum::<f64>();
+                    values.iter_mut().for_each(|p| *p /= sum);
+                }
+            }
+        }
+    }
+
+    /**
+     * Derivatives of apply, [probability][logit].
+     */
+    pub fn jacobian(&self, logits: &[f64], exclusive: bool) -> Vec<Vec<f64>> {
+        let p = self.apply(logits, exclusive);
+        let kronecker = |k: usize, j: usize| (k == j) as u8 as f64;
+        let jacobian = |d: &dyn Fn(usize, usize) -> f64| {
+            (0..logits.len())
+                .map(|k| (0..logits.len()).map(|j| d(k, j)).collect())
+                .collect()
+        };
+        match self {
+            Calibration::None => jacobian(&kronecker),
+            Calibration::Temperature(t) if exclusive => {
+                jacobian(&|k, j| p[k] * (kronecker(k, j) - p[j]) / t)
+            }
+            Calibration::Temperature(t) => {
+                jacobian(&|k, j| kronecker(k, j) * p[k] * (1.0 - p[k]) / t)
+            }
+            Calibration::Platt(parameters) => {
+                let s = logits
+                    .iter()
+                    .zip(parameters)
+                    .map(|(z, (a, b))| sigmoid(a * z + b))
+                    .collect::<Vec<_>>();
+                // ∂s/∂z of every class
+                let ds = s
+                    .iter()
+                    .zip(parameters)
+                    .map(|(s, (a, _))| a * s * (1.0 - s))
+                    .collect::<Vec<_>>();
+                if exclusive {
+                    let sum = s.iter().sum::<f64>();
+                    jacobian(&|k, j| ds[j] * (kronecker(k, j) - p[k]) / sum)
+                } else {
+                    jacobian(&|k, j| kronecker(k, j) * ds[k])
+                }
+            }
+        }
+    }
+}
+
+/**
+ * Finds the temperature minimizing the negative log likelihood of the targets.
+ *
+ * golden section search over ln(T), the loss is unimodal in T.
+ */
+pub fn fit_temperature(logits: &[Vec<f64>], targets: &[Vec<f64>], exclusive: bool) -> f64 {
+    let loss = |t: f64| -> f64 {
+        let calibration = Calibration::Temperature(t);
+        logits
+            .iter()
+            .zip(targets)
+            .map(|(z, y_desired)| {
+                let p = calibration.apply(z, exclusive);
+                p.iter()
+                    .zip(y_desired)
+                    .map(|(&p, &t)| {
+                        let p = p.clamp(f64::EPSILON, 1.0 - f64::EPSILON);
+                        if exclusive {
+                            -t * p.ln()
+                        } else {
+                            -t * p.ln() - (1.0 - t) * (1.0 - p).ln()
+                        }
+                    })
+                    .sum::<f64>()
+            })
+            .sum()
+    };
+
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut a, mut b) = (0.05f64.ln(), 20f64.ln());
+    let mut c = b - ratio * (b - a);
+    let mut d = a + ratio * (b - a);
+    let (mut fc, mut fd) = (loss(c.exp()), loss(d.exp()));
+    for _ in 0..60 {
+        if fc < fd {
+            b = d;
+            d = c;
+            fd = fc;
+            c = b - ratio * (b - a);
+            fc = loss(c.exp());
+        } else {
+            a = c;
+            c = d;
+            fc = fd;
+            d = a + ratio * (b - a);
+            fd = loss(d.exp());
+        }
+    }
+    ((a + b) / 2.0).exp()
+}
+
+/**
+ * Platt scaling: fits sigmoid(a * z + b) to the binary labels with Newton's method.
+ *
+ * uses Platt's smoothed targets to avoid overfitting small validation sets.
+ */
+pub fn fit_platt(logits: &[f64], positives: &[bool]) -> (f64, f64) {
+    let n_positive = positives.iter().filter(|&&p| p).count() as f64;
+    let n_negative = positives.len() as f64 - n_positive;
+    let high = (n_positive + 1.0) / (n_positive + 2.0);
+    let low = 1.0 / (n_negative + 2.0);
+
+    let (mut a, mut b) = (1.0, 0.0);
+    for _ in 0..100 {
+        // gradient and hessian of the cross entropy with respect to (a, b)
+        let (mut ga, mut gb, mut haa, mut hab, mut hbb) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        logits.iter().zip(positives).for_each(|(&z, &positive)| {
+            let t = if positive { high } else { low };
+            let p = sigmoid(a * z + b);
+            let w = p * (1.0 - p);
+            ga += (p - t) * z;
+            gb += p - t;
+            haa += w * z * z;
+            hab += w * z;
+            hbb += w;
+        });
+        // small ridge keeps the system invertible when the classes are separable
+        let (haa, hbb) = (haa + 1e-9, hbb + 1e-9);
+        let det = haa * hbb - hab * hab;
+        if det.abs() < 1e-15 {
+            break;
+        }
+        let da = (hbb * ga - hab * gb) / det;
+        let db = (haa * gb - hab * ga) / det;
+        a -= da;
+        b -= db;
+        if da.abs() < 1e-10 && db.abs() < 1e-10 {
+            break;
+        }
+    }
+    (a, b)
+}
+
+fn sigmoid(z: f64) -> f64 {
+    1.0 / (1.0 + (-z).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expected_calibration_error() {
+        // 0.9 confident but only half right
+        let samples = [(0.9, true), (0.9, false), (0.3, false), (0.3, false)];
+        let report = CalibrationReport::new(Split::Testing, Calibration::None, &samples, 10);
+        assert!((report.expected_calibration_error - (0.5 * 0.4 + 0.5 * 0.3)).abs() < 1e-12);
+        assert_eq!(report.bins[9].count, 2);
+    }
+
+    #[test]
+    fn temperature_softens_overconfident_logits() {
+        // logits are twice as confident as the labels justify
+        let logits = vec![vec![4.0, 0.0]; 200];
+        let targets = (0..200)
+            .map(|i| {
+                if i % 4 == 0 {
+                    vec![0.0, 1.0]
+                } else {
+                    vec![1.0, 0.0]
+                }
+            })
+            .collect::<Vec<_>>();
+        let t = fit_temperature(&logits, &targets, true);
+        // softmax(4 / T) = 0.75 => T = 4 / ln(3)
+        assert!((t - 4.0 / 3f64.ln()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn platt_recovers_frequencies() {
+        let logits = vec![1.0; 100];
+        let positives = (0..100).map(|i| i % 5 != 0).collect::<Vec<_>>();
+        let (a, b) = fit_platt(&logits, &positives);
+        // the smoothed targets barely move the fitted probability away from 0.8
+        assert!((sigmoid(a + b) - 0.8).abs() < 0.01);
+    }
+}
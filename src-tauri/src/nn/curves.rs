@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+
+use super::{metrics::ratio, Split};
+
+/**
+ * One-vs-rest ROC and precision-recall curves of every class for one split.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurveReport {
+    pub split: Split,
+    pub classes: Vec<Curves>,
+    // all (score, label) pairs of every class pooled together
+    pub micro: Curves,
+    // averages over the classes that have positive samples
+    pub macro_roc_auc: f64,
+    pub macro_average_precision: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Curves {
+    // (false positive rate, true positive rate)
+    pub roc: Vec<(f64, f64)>,
+    // (recall, precision)
+    pub precision_recall: Vec<(f64, f64)>,
+    pub roc_auc: f64,
+    pub average_precision: f64,
+    pub positives: usize,
+}
+
+impl CurveReport {
+    /**
+     * scores[i][j] is the predicted probability of class j for sample i,
+     * positives[i][j] whether sample i belongs to class j.
+     */
+    pub fn new(split: Split, scores: &[Vec<f64>], positives: &[Vec<bool>]) -> Self {
+        let classes_count = scores.first().map_or(0, |row| row.len());
+        let classes = (0..classes_count)
+            .map(|j| {
+                Curves::new(
+                    scores
+                        .iter()
+                        .zip(positives)
+                        .map(|(s, p)| (s[j], p[j]))
+                        .collect(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let micro = Curves::new(
+            scores
+                .iter()
+                .zip(positives)
+                .flat_map(|(s, p)| s.iter().cloned().zip(p.iter().cloned()))
+                .collect(),
+        );
+
+        let present = classes.iter().filter(|c| c.positives > 0);
+        let count = present.clone().count().max(1) as f64;
+        let macro_roc_auc = present.clone().map(|c| c.roc_auc).sum::<f64>() / count;
+        let macro_average_precision = present.map(|c| c.average_precision).sum::<f64>() / count;
+
+        CurveReport {
+            split,
+            classes,
+            micro,
+            macro_roc_auc,
+            macro_average_precision,
+        }
+    }
+}
+
+impl Curves {
+    pub fn new(mut samples: Vec<(f64, bool)>) -> Self {
+        // highest scores first, every distinct score is a threshold
+        samples.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let positives = samples.iter().filter(|(_, p)| *p).count();
+        let negatives = samples.len() - positives;
+
+        let mut roc = vec![(0.0, 0.0)];
+        let mut precision_recall = vec![(0.0, 1.0)];
+        let mut average_precision = 0.0;
+        let (mut tp, mut fp) = (0, 0);
+
+        for (i, &(score, positive)) in samples.iter().enumerate() {
+            if positive {
+                tp += 1;
+            } else {
+                fp += 1;
+            }
+            // only emit a point once all samples sharing this score are counted
+            if samples.get(i + 1).is_some_and(|next| next.0 == score) {
+                continue;
+            }
+            let tpr = ratio(tp, positives);
+            roc.push((ratio(fp, negatives), tpr));
+
+            let precision = ratio(tp, tp + fp);
+            let previous_recall = precision_recall.last().unwrap().0;
+            average_precision += (tpr - previous_recall) * precision;
+            precision_recall.push((tpr, precision));
+        }
+
+        // trapezoidal rule
+        let roc_auc = roc
+            .windows(2)
+            .map(|w| (w[1].0 - w[0].0) * (w[1].1 + w[0].1) / 2.0)
+            .sum::<f64>();
+
+        Curves {
+            roc,
+            precision_recall,
+            roc_auc,
+            average_precision,
+            positives,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perfect_and_random_rankings() {
+        let perfect = Curves::new(vec![(0.9, true), (0.8, true), (0.3, false), (0.1, false)]);
+        assert_eq!(perfect.roc_auc, 1.0);
+        assert_eq!(perfect.average_precision, 1.0);
+
+        let tied = Curves::new(vec![(0.5, true), (0.5, false), (0.5, true), (0.5, false)]);
+        assert_eq!(tied.roc, vec![(0.0, 0.0), (1.0, 1.0)]);
+        assert_eq!(tied.roc_auc, 0.5);
+        assert_eq!(tied.average_precision, 0.5);
+    }
+
+    #[test]
+    fn matches_known_values() {
+        // sklearn: roc_auc_score = 0.75, average_precision_score = 0.8333
+        let curves = Curves::new(vec![(0.1, false), (0.4, false), (0.35, true), (0.8, true)]);
+        assert!((curves.roc_auc - 0.75).abs() < 1e-12);
+        assert!((curves.average_precision - 5.0 / 6.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,635 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    argmax, calibration::Calibration, float::Float, matrix::Matrix, NeuralNetwork, Split, TaskType,
+};
+
+/**
+ * Gradients of every output with respect to the inputs of one sample.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Saliency {
+    // outputs of the network before calibration
+    pub outputs: Vec<f64>,
+    // [output][input] ∂output/∂input in raw (not normalized) input units
+    pub gradients: Vec<Vec<f64>>,
+}
+
+/**
+ * How much worse the network gets on a split when one input feature is shuffled.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermutationImportance {
+    pub split: Split,
+    // accuracy (classification, multi-label) or MSE (regression) of the untouched data
+    pub baseline: f64,
+    // one entry per input feature
+    pub features: Vec<FeatureImportance>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureImportance {
+    // accuracy lost or MSE gained, averaged over the repeats
+    pub mean: f64,
+    pub std: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AttributionMethod {
+    IntegratedGradients,
+    KernelShap,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttributionSettings {
+    pub method: AttributionMethod,
+    // raw inputs the sample is compared to, the mean training inputs when missing
+    #[serde(default)]
+    pub baseline: Option<Vec<f64>>,
+    // integrated gradients path steps, 0 uses 50
+    #[serde(default)]
+    pub steps: usize,
+    // KernelSHAP coalitions, 0 uses 256. small inputs enumerate every coalition instead
+    #[serde(default)]
+    pub samples: usize,
+}
+
+/**
+ * Per input contributions to the difference between the outputs of a sample and of a baseline.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attributions {
+    pub method: AttributionMethod,
+    pub baseline: Vec<f64>,
+    pub baseline_outputs: Vec<f64>,
+    pub outputs: Vec<f64>,
+    // [output][input], every row sums to outputs - baseline_outputs
+    // (up to the path discretization for integrated gradients)
+    pub values: Vec<Vec<f64>>,
+}
+
+impl<T: Float> NeuralNetwork<T> {
+    /**
+     * Input gradients of one raw sample, backpropagated through the layers like the errors while training.
+     */
+    #[allow(dead_code)]
+    pub fn saliency(&self, inputs: &[f64]) -> Result<Saliency, String> {
+        let inputs_count = self.layers[0].weights.cols();
+        if inputs.len() != inputs_count {
+            return Err(format!("Expected {} inputs", inputs_count));
+        }
+        Ok(self.input_gradients(inputs, false))
+    }
+
+    // the same for the calibrated outputs of predict when calibrated is set
+    fn input_gradients(&self, inputs: &[f64], calibrated: bool) -> Saliency {
+        // forward, keeping the weighted sums for the derivatives
+        let mut layer_inputs = Matrix::zeros(1, self.layers[0].weights.cols());
+        self.normalize_into(inputs, layer_inputs.row_mut(0));
+        let x = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let x = layer.weighted_sums(&layer_inputs);
+                layer_inputs = layer.activate(&x);
+                x
+            })
+            .collect::<Vec<_>>();
+        // predict applies the calibration to the output layer weighted sums
+        let (output_layer, hidden_layers) = self.layers.split_last().unwrap();
+        let logits = x.last().unwrap().row(0);
+        let (outputs, jacobian) = match &self.calibration {
+            calibration @ (Calibration::Temperature(_) | Calibration::Platt(_)) if calibrated => {
+                let logits = logits.iter().map(|z| z.as_f64()).collect::<Vec<_>>();
+                let exclusive = self.exclusive_outputs();
+                let jacobian = calibration
+                    .jacobian(&logits, exclusive)
+                    .iter()
+                    .map(|row| row.iter().map(|&d| T::from_f64(d)).collect::<Vec<_>>())
+                    .collect::<Vec<_>>();
+                (
+                    calibration.apply(&logits, exclusive),
+                    Matrix::from_rows(&jacobian),
+                )
+            }
+            _ => (
+                layer_inputs.row(0).iter().map(|y| y.as_f64()).collect(),
+                output_layer.jacobian(logits),
+            ),
+        };
+
+        // backward, one row of errors per output
+        let mut errors = jacobian.mul(&output_layer.weights);
+        for (layer, x) in hidden_layers.iter().zip(&x).rev() {
+            let x = Matrix::from_rows(&vec![x.row(0); errors.rows()]);
+            let (gradiant_errors, _) = layer.error_signals(&x, &errors);
+            errors = gradiant_errors.mul(&layer.weights);
+        }
+
+        let gradients = errors
+            .iter_rows()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .map(|(i, g)| match self.normalization_factors.get(i) {
+                        Some(nf) => g.as_f64() / (nf.1 - nf.0),
+                        None => g.as_f64(),
+                    })
+                    .collect()
+            })
+            .collect();
+        Saliency { outputs, gradients }
+    }
+
+    /**
+     * Shuffles every input feature of the split repeats times and measures the score lost.
+     *
+     * seeded networks shuffle the same way every time.
+     */
+    #[allow(dead_code)]
+    pub fn permutation_importance(
+        &self,
+        split: Split,
+        repeats: usize,
+    ) -> Result<PermutationImportance, String> {
+        let data = self.data(split);
+        if data.is_empty() {
+            return Err(format!("The {:?} data is empty", split));
+        }
+        let repeats = repeats.max(1);
+        let baseline = self.score(data);
+        let features_count = self.layers[0].weights.cols();
+        let seed = self.seed.unwrap_or_else(rand::random);
+
+        let features = (0..features_count)
+            .map(|feature| {
+                let losses = (0..repeats)
+                    .into_par_iter()
+                    .map(|repeat| {
+                        let mut rng =
+                            StdRng::seed_from_u64(seed ^ ((feature * repeats + repeat) as u64));
+                        let mut column = data.iter().map(|(x, _)| x[feature]).collect::<Vec<_>>();
+                        column.shuffle(&mut rng);
+                        let permuted = data
+                            .iter()
+                            .zip(column)
+                            .map(|((x, y), v)| {
+                                let mut x = x.clone();
+                                x[feature] = v;
+                                (x, y.clone())
+                            })
+                            .collect::<Vec<_>>();
+                        self.score_loss(baseline, self.score(&permuted))
+                    })
+                    .collect::<Vec<_>>();
+                let mean = losses.iter().sum::<f64>() / repeats as f64;
+                let variance =
+                    losses.iter().map(|l| (l - mean).powi(2)).sum::<f64>() / repeats as f64;
+                FeatureImportance {
+                    mean,
+                    std: variance.sqrt(),
+                }
+            })
+            .collect();
+
+        Ok(PermutationImportance {
+            split,
+            baseline,
+            features,
+        })
+    }
+
+    /**
+     * Attributions of one raw sample with the given method.
+     *
+     * both methods explain predict, calibrated probabilities included.
+     */
+    #[allow(dead_code)]
+    pub fn attributions(
+        &self,
+        inputs: &[f64],
+        settings: &AttributionSettings,
+    ) -> Result<Attributions, String> {
+        let inputs_count = self.layers[0].weights.cols();
+        if inputs.len() != inputs_count {
+            return Err(format!("Expected {} inputs", inputs_count));
+        }
+        let baseline = match &settings.baseline {
+            Some(baseline) if baseline.len() != inputs_count => {
+                return Err(format!("Expected a baseline of {} inputs", inputs_count))
+            }
+            Some(baseline) => baseline.clone(),
+            None => self.default_baseline(),
+        };
+        Ok(match settings.method {
+            AttributionMethod::IntegratedGradients => {
+                let steps = if settings.steps > 0 {
+                    settings.steps
+                } else {
+                    50
+                };
+                self.integrated_gradients(inputs, &baseline, steps)
+            }
+            AttributionMethod::KernelShap => {
+                let samples = if settings.samples > 0 {
+                    settings.samples
+                } else {
+                    256
+                };
+                self.kernel_shap(inputs, &baseline, samples)
+            }
+        })
+    }
+
+    /**
+     * The mean raw training inputs, or the middle of the input ranges without training data.
+     */
+    pub fn default_baseline(&self) -> Vec<f64> {
+        let nf = &self.normalization_factors;
+        let denormalize = |i: usize, v: f64| match nf.get(i) {
+            Some(&(min, max)) if min <= max => min + v * (max - min),
+            _ => v,
+        };
+        (0..self.layers[0].weights.cols())
+            .map(|i| {
+                if self.training_data.is_empty() {
+                    denormalize(i, 0.5)
+                } else {
+                    let sum = self
+                        .training_data
+                        .iter()
+                        .map(|(x, _)| x[i].as_f64())
+                        .sum::<f64>();
+                    denormalize(i, sum / self.training_data.len() as f64)
+                }
+            })
+            .collect()
+    }
+
+    /**
+     * (x - baseline) times the input gradients of predict averaged along the straight path
+     * from the baseline.
+     */
+    pub fn integrated_gradients(
+        &self,
+        inputs: &[f64],
+        baseline: &[f64],
+        steps: usize,
+    ) -> Attributions {
+        let steps = steps.max(1);
+        let point = |alpha: f64| {
+            baseline
+                .iter()
+                .zip(inputs)
+                .map(|(b, x)| b + alpha * (x - b))
+                .collect::<Vec<_>>()
+        };
+        // midpoint rule over the path
+        let gradients = (0..steps)
+            .into_par_iter()
+            .map(|step| {
+                self.input_gradients(&point((step as f64 + 0.5) / steps as f64), true)
+                    .gradients
+            })
+            .reduce_with(|mut a, b| {
+                a.iter_mut()
+                    .flatten()
+                    .zip(b.iter().flatten())
+                    .for_each(|(a, b)| *a += b);
+                a
+            })
+            .unwrap_or_default();
+        let values = gradients
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(inputs.iter().zip(baseline))
+                    .map(|(g, (x, b))| (x - b) * g / steps as f64)
+                    .collect()
+            })
+            .collect();
+        Attributions {
+            method: AttributionMethod::IntegratedGradients,
+            baseline: baseline.to_vec(),
+            baseline_outputs: self.predict(&baseline.to_vec()),
+            outputs: self.predict(&inputs.to_vec()),
+            values,
+        }
+    }
+
+    /**
+     * Shapley values of predict estimated by weighted least squares over feature coalitions.
+     *
+     * features outside a coalition take their baseline value. with at most samples coalitions
+     * they are all enumerated and the values are exact.
+     */
+    pub fn kernel_shap(&self, inputs: &[f64], baseline: &[f64], samples: usize) -> Attributions {
+        let m = inputs.len();
+        let baseline_outputs = self.predict(&baseline.to_vec());
+        let outputs = self.predict(&inputs.to_vec());
+        let differences = outputs
+            .iter()
+            .zip(&baseline_outputs)
+            .map(|(y, b)| y - b)
+            .collect::<Vec<_>>();
+        if m < 2 {
+            return Attributions {
+                method: AttributionMethod::KernelShap,
+                baseline: baseline.to_vec(),
+                values: differences.iter().map(|&d| vec![d; m]).collect(),
+                baseline_outputs,
+                outputs,
+            };
+        }
+
+        // (coalition, weight), the empty and the full coalitions are the constraints below
+        let coalitions = if m < usize::BITS as usize && (1usize << m) - 2 <= samples {
+            (1..(1usize << m) - 1)
+                .map(|mask| {
+                    let coalition = (0..m).map(|i| mask & (1 << i) != 0).collect::<Vec<_>>();
+                    let size = coalition.iter().filter(|&&c| c).count();
+                    (coalition, shapley_kernel(m, size))
+                })
+                .collect::<Vec<_>>()
+        } else {
+            // sizes drawn from the kernel so every coalition has the same weight
+            let sizes = (1..m)
+                .map(|s| shapley_kernel(m, s) * binomial(m, s))
+                .collect::<Vec<_>>();
+            let total = sizes.iter().sum::<f64>();
+            let mut rng = match self.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            };
+            let mut features = (0..m).collect::<Vec<_>>();
+            (0..samples.max(1))
+                .map(|_| {
+                    let mut r = rng.gen::<f64>() * total;
+                    let size = 1 + sizes
+                        .iter()
+                        .position(|&w| {
+                            r -= w;
+                            r <= 0.0
+                        })
+                        .unwrap_or(m - 2);
+                    features.shuffle(&mut rng);
+                    let mut coalition = vec![false; m];
+                    features[..size].iter().for_each(|&i| coalition[i] = true);
+                    (coalition, 1.0)
+                })
+                .collect()
+        };
+
+        let masked = coalitions
+            .iter()
+            .map(|(coalition, _)| {
+                coalition
+                    .iter()
+                    .zip(inputs.iter().zip(baseline))
+                    .map(|(&c, (&x, &b))| if c { x } else { b })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let predictions = self.predict_many(&masked);
+
+        // the last value is Δ minus the others: y - z_m Δ = Σ φ_i (z_i - z_m) for i < m
+        let z = |c: &[bool], i: usize| c[i] as u8 as f64;
+        let design = coalitions
+            .iter()
+            .map(|(c, _)| {
+                (0..m - 1)
+                    .map(|i| z(c, i) - z(c, m - 1))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let mut a = vec![vec![0.0; m - 1]; m - 1];
+        design.iter().zip(&coalitions).for_each(|(d, (_, w))| {
+            a.iter_mut().zip(d).for_each(|(row, &d_i)| {
+                row.iter_mut()
+                    .zip(d)
+                    .for_each(|(a, &d_j)| *a += w * d_i * d_j)
+            });
+        });
+        let values = differences
+            .iter()
+            .enumerate()
+            .map(|(k, &difference)| {
+                let mut b = vec![0.0; m - 1];
+                coalitions
+                    .iter()
+                    .zip(&design)
+                    .enumerate()
+                    .for_each(|(s, ((c, w), d))| {
+                        let y =
+                            predictions.row(s)[k] - baseline_outputs[k] - z(c, m - 1) * difference;
+                        b.iter_mut().zip(d).for_each(|(b, &d_i)| *b += w * d_i * y);
+                    });
+                let mut phi = solve(a.clone(), b).unwrap_or_else(|| vec![0.0; m - 1]);
+                phi.push(difference - phi.iter().sum::<f64>());
+                phi
+            })
+            .collect();
+
+        Attributions {
+            method: AttributionMethod::KernelShap,
+            baseline: baseline.to_vec(),
+            baseline_outputs,
+            outputs,
+            values,
+        }
+    }
+
+    // accuracy, or MSE for regression
+    fn score(&self, data: &[(Vec<T>, Vec<T>)]) -> f64 {
+        match self.task_type {
+            TaskType::Regression => self.calculate_mse(data),
+            TaskType::Classification => {
+                data.iter()
+                    .filter(|(inputs, y_desired)| {
+                        argmax(&self.predict_normalized(inputs)) == argmax(y_desired)
+                    })
+                    .count() as f64
+                    / data.len() as f64
+            }
+            TaskType::MultiLabel => {
+                let (correct, total) =
+                    data.iter()
+                        .fold((0, 0), |(correct, total), (inputs, y_desired)| {
+                            let outputs = self.predict_normalized(inputs);
+                            let positives = self.positives(y_desired);
+                            let hits = outputs
+                                .iter()
+                                .zip(&positives)
+                                .enumerate()
+                                .filter(|(i, (&p, &positive))| {
+                                    (p >= self.label_threshold(*i)) == positive
+                                })
+                                .count();
+                            (correct + hits, total + positives.len())
+                        });
+                correct as f64 / total.max(1) as f64
+            }
+        }
+    }
+
+    // how much worse the score got, positive when the feature matters
+    fn score_loss(&self, baseline: f64, score: f64) -> f64 {
+        match self.task_type {
+            TaskType::Regression => score - baseline,
+            _ => baseline - score,
+        }
+    }
+}
+
+// weight of a coalition of size features out of m
+fn shapley_kernel(m: usize, size: usize) -> f64 {
+    (m - 1) as f64 / (binomial(m, size) * size as f64 * (m - size) as f64)
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    (0..k).fold(1.0, |c, i| c * (n - i) as f64 / (i + 1) as f64)
+}
+
+/**
+ * Solves a · x = b by gaussian elimination with partial pivoting.
+ *
+ * Returns None for a singular matrix.
+ */
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let (top, bottom) = a.split_at_mut(col + 1);
+        let pivot_row = &top[col];
+        for (r, row) in bottom.iter_mut().enumerate() {
+            let factor = row[col] / pivot_row[col];
+            row[col..]
+                .iter_mut()
+                .zip(&pivot_row[col..])
+                .for_each(|(a, p)| *a -= factor * p);
+            b[col + 1 + r] -= factor * b[col];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum = (row + 1..n).map(|k| a[row][k] * x[k]).sum::<f64>();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{activation_functions::ActivationFunction, calibration::CalibrationMethod};
+    use super::*;
+
+    fn network() -> NeuralNetwork {
+        // the class only depends on the first input
+        let data = (0..200)
+            .map(|i| {
+                let x = (i * 37 % 200) as f64;
+                let class = (x >= 100.) as usize;
+                (
+                    vec![x, (i * 53 % 200) as f64],
+                    (0..2).map(|c| (c == class) as u8 as f64).collect(),
+                )
+            })
+            .collect::<Vec<(Vec<f64>, Vec<f64>)>>();
+        let mut nn = NeuralNetwork::with_seed(
+            Some(5),
+            2,
+            vec![6],
+            &[ActivationFunction::Tanh],
+            2,
+            ActivationFunction::Softmax,
+            0.5,
+            data.clone(),
+            vec![],
+            data,
+        );
+        (0..50).for_each(|_| {
+            nn.epoch();
+        });
+        nn
+    }
+
+    #[test]
+    fn saliency_matches_finite_differences() {
+        let nn = network();
+        let inputs = [40.0, 120.0];
+        let saliency = nn.saliency(&inputs).unwrap();
+        assert!(nn.saliency(&inputs[..1]).is_err());
+        let h = 1e-4;
+        for i in 0..2 {
+            let (mut plus, mut minus) = (inputs.to_vec(), inputs.to_vec());
+            plus[i] += h;
+            minus[i] -= h;
+            let (plus, minus) = (nn.predict(&plus), nn.predict(&minus));
+            for k in 0..2 {
+                let numeric = (plus[k] - minus[k]) / (2. * h);
+                assert!((saliency.gradients[k][i] - numeric).abs() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn permutation_importance_finds_the_relevant_feature() {
+        let importance = network().permutation_importance(Split::Testing, 3).unwrap();
+        assert!(importance.baseline > 0.9);
+        assert!(importance.features[0].mean > importance.features[1].mean + 0.2);
+    }
+
+    #[test]
+    fn attributions_sum_to_the_output_difference() {
+        let nn = network();
+        let (inputs, baseline) = ([40.0, 120.0], nn.default_baseline());
+
+        let shap = nn.kernel_shap(&inputs, &baseline, 256);
+        let f = |x: [f64; 2]| nn.predict(&x.to_vec());
+        let (fx, fb) = (f(inputs), f([baseline[0], baseline[1]]));
+        let (f0, f1) = (f([inputs[0], baseline[1]]), f([baseline[0], inputs[1]]));
+        for k in 0..2 {
+            // exact Shapley values of two players
+            let phi0 = ((f0[k] - fb[k]) + (fx[k] - f1[k])) / 2.;
+            assert!((shap.values[k][0] - phi0).abs() < 1e-9);
+            assert!((shap.values[k].iter().sum::<f64>() - (fx[k] - fb[k])).abs() < 1e-9);
+        }
+
+        let ig = nn.integrated_gradients(&inputs, &baseline, 200);
+        for k in 0..2 {
+            let difference = ig.outputs[k] - ig.baseline_outputs[k];
+            assert!((ig.values[k].iter().sum::<f64>() - difference).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn calibrated_attributions_explain_predict() {
+        let mut nn = network();
+        nn.validation_data = nn.testing_data.clone();
+        let (inputs, baseline) = ([40.0, 120.0], nn.default_baseline());
+        for method in [CalibrationMethod::Temperature, CalibrationMethod::Platt] {
+            nn.calibrate(method).unwrap();
+            let (fx, fb) = (nn.predict(&inputs.to_vec()), nn.predict(&baseline));
+            let ig = nn.integrated_gradients(&inputs, &baseline, 200);
+            let shap = nn.kernel_shap(&inputs, &baseline, 256);
+            for k in 0..2 {
+                assert_eq!((ig.outputs[k], ig.baseline_outputs[k]), (fx[k], fb[k]));
+                assert!((ig.values[k].iter().sum::<f64>() - (fx[k] - fb[k])).abs() < 1e-3);
+                assert!((shap.values[k].iter().sum::<f64>() - (fx[k] - fb[k])).abs() < 1e-9);
+            }
+        }
+    }
+}
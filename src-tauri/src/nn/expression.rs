@@ -0,0 +1,499 @@
+/**
+ * Tiny math expression language used by custom activation functions.
+ *
+ * Expressions have a single variable `x`, the operators + - * / ^ (or **),
+ * the constants `pi` and `e` and the functions listed in `Function`.
+ * Derivatives are computed symbolically.
+ */
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Number(f64),
+    Variable,
+    Neg(Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Sub(Box<Expression>, Box<Expression>),
+    Mul(Box<Expression>, Box<Expression>),
+    Div(Box<Expression>, Box<Expression>),
+    Pow(Box<Expression>, Box<Expression>),
+    Function(Function, Box<Expression>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Function {
+    Abs,
+    Sign,
+    Exp,
+    Ln,
+    Sqrt,
+    Sin,
+    Cos,
+    Tan,
+    Sinh,
+    Cosh,
+    Tanh,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "abs" => Some(Function::Abs),
+            "sign" => Some(Function::Sign),
+            "exp" => Some(Function::Exp),
+            "ln" | "log" => Some(Function::Ln),
+            "sqrt" => Some(Function::Sqrt),
+            "sin" => Some(Function::Sin),
+            "cos" => Some(Function::Cos),
+            "tan" => Some(Function::Tan),
+            "sinh" => Some(Function::Sinh),
+            "cosh" => Some(Function::Cosh),
+            "tanh" => Some(Function::Tanh),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, v: f64) -> f64 {
+        match self {
+            Function::Abs => v.abs(),
+            Function::Sign => {
+                if v > 0. {
+                    1.
+                } else if v < 0. {
+                    -1.
+                } else {
+                    0.
+                }
+            }
+            Function::Exp => v.exp(),
+            Function::Ln => v.ln(),
+            Function::Sqrt => v.sqrt(),
+            Function::Sin => v.sin(),
+            Function::Cos => v.cos(),
+            Function::Tan => v.tan(),
+            Function::Sinh => v.sinh(),
+            Function::Cosh => v.cosh(),
+            Function::Tanh => v.tanh(),
+        }
+    }
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Expression, String> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            position: 0,
+            depth: 0,
+        };
+        let expression = parser.expression()?;
+        parser.skip_whitespace();
+        if parser.position < parser.chars.len() {
+            return Err(format!(
+                "unexpected `{}` at position {}",
+                parser.chars[parser.position], parser.position
+            ));
+        }
+        Ok(expression)
+    }
+
+    pub fn evaluate(&self, x: f64) -> f64 {
+        match self {
+            Expression::Number(n) => *n,
+            Expression::Variable => x,
+            Expression::Neg(a) => -a.evaluate(x),
+            Expression::Add(a, b) => a.evaluate(x) + b.evaluate(x),
+            Expression::Sub(a, b) => a.evaluate(x) - b.evaluate(x),
+            Expression::Mul(a, b) => a.evaluate(x) * b.evaluate(x),
+            Expression::Div(a, b) => a.evaluate(x) / b.evaluate(x),
+            Expression::Pow(a, b) => a.evaluate(x).powf(b.evaluate(x)),
+            Expression::Function(f, a) => f.apply(a.evaluate(x)),
+        }
+    }
+
+    fn is_constant(&self) -> bool {
+        match self {
+            Expression::Number(_) => true,
+            Expression::Variable => false,
+            Expression::Neg(a) | Expression::Function(_, a) => a.is_constant(),
+            Expression::Add(a, b)
+            | Expression::Sub(a, b)
+            | Expression::Mul(a, b)
+            | Expression::Div(a, b)
+            | Expression::Pow(a, b) => a.is_constant() && b.is_constant(),
+        }
+    }
+
+    /**
+     * Symbolic derivative with respect to x.
+     */
+    pub fn derivative(&self) -> Expression {
+        match self {
+            Expression::Number(_) => Expression::Number(0.),
+            Expression::Variable => Expression::Number(1.),
+            Expression::Neg(a) => neg(a.derivative()),
+            Expression::Add(a, b) => add(a.derivative(), b.derivative()),
+            Expression::Sub(a, b) => sub(a.derivative(), b.derivative()),
+            // (ab)' = a'b + ab'
+            Expression::Mul(a, b) => add(
+                mul(a.derivative(), *b.clone()),
+                mul(*a.clone(), b.derivative()),
+            ),
+            // (a/b)' = (a'b - ab') / b^2
+            Expression::Div(a, b) => div(
+                sub(
+                    mul(a.derivative(), *b.clone()),
+                    mul(*a.clone(), b.derivative()),
+                ),
+                pow(*b.clone(), Expression::Number(2.)),
+            ),
+            // (a^n)' = n a^(n-1) a'
+            Expression::Pow(a, b) if b.is_constant() => mul(
+                mul(
+                    *b.clone(),
+                    pow(*a.clone(), sub(*b.clone(), Expression::Number(1.))),
+                ),
+                a.derivative(),
+            ),
+            // (a^b)' = a^b (b' ln(a) + b a' / a)
+            Expression::Pow(a, b) => mul(
+                self.clone(),
+                add(
+                    mul(b.derivative(), function(Function::Ln, *a.clone())),
+                    div(mul(*b.clone(), a.derivative()), *a.clone()),
+                ),
+            ),
+            // chain rule: f(a)' = f'(a) a'
+            Expression::Function(f, a) => {
+                let inner = *a.clone();
+                let outer = match f {
+                    Function::Abs => function(Function::Sign, inner),
+                    Function::Sign => Expression::Number(0.),
+                    Function::Exp => function(Function::Exp, inner),
+                    Function::Ln => div(Expression::Number(1.), inner),
+                    Function::Sqrt => div(
+                        Expression::Number(1.),
+                        mul(Expression::Number(2.), function(Function::Sqrt, inner)),
+                    ),
+                    Function::Sin => function(Function::Cos, inner),
+                    Function::Cos => neg(function(Function::Sin, inner)),
+                    Function::Tan => div(
+                        Expression::Number(1.),
+                        pow(function(Function::Cos, inner), Expression::Number(2.)),
+                    ),
+                    Function::Sinh => function(Function::Cosh, inner),
+                    Function::Cosh => function(Function::Sinh, inner),
+                    Function::Tanh => sub(
+                        Expression::Number(1.),
+                        pow(function(Function::Tanh, inner), Expression::Number(2.)),
+                    ),
+                };
+                mul(outer, a.derivative())
+            }
+        }
+    }
+}
+
+// constructors folding the trivial cases so derivatives stay small
+
+fn number(e: &Expression) -> Option<f64> {
+    match e {
+        Expression::Number(n) => Some(*n),
+        _ => None,
+    }
+}
+
+fn neg(a: Expression) -> Expression {
+    match number(&a) {
+        Some(n) => Expression::Number(-n),
+        None => Expression::Neg(Box::new(a)),
+    }
+}
+
+fn add(a: Expression, b: Expression) -> Expression {
+    match (number(&a), number(&b)) {
+        (Some(x), Some(y)) => Expression::Number(x + y),
+        (Some(0.), _) => b,
+        (_, Some(0.)) => a,
+        _ => Expression::Add(Box::new(a), Box::new(b)),
+    }
+}
+
+fn sub(a: Expression, b: Expression) -> Expression {
+    match (number(&a), number(&b)) {
+        (Some(x), Some(y)) => Expression::Number(x - y),
+        (Some(0.), _) => neg(b),
+        (_, Some(0.)) => a,
+        _ => Expression::Sub(Box::new(a), Box::new(b)),
+    }
+}
+
+fn mul(a: Expression, b: Expression) -> Expression {
+    match (number(&a), number(&b)) {
+        (Some(x), Some(y)) => Expression::Number(x * y),
+        (Some(0.), _) | (_, Some(0.)) => Expression::Number(0.),
+        (Some(1.), _) => b,
+        (_, Some(1.)) => a,
+        _ => Expression::Mul(Box::new(a), Box::new(b)),
+    }
+}
+
+fn div(a: Expression, b: Expression) -> Expression {
+    match (number(&a), number(&b)) {
+        (Some(0.), _) => Expression::Number(0.),
+        (_, Some(1.)) => a,
+        _ => Expression::Div(Box::new(a), Box::new(b)),
+    }
+}
+
+fn pow(a: Expression, b: Expression) -> Expression {
+    match number(&b) {
+        Some(1.) => a,
+        Some(0.) => Expression::Number(1.),
+        _ => Expression::Pow(Box::new(a), Box::new(b)),
+    }
+}
+
+fn function(f: Function, a: Expression) -> Expression {
+    Expression::Function(f, Box::new(a))
+}
+
+/**
+ * Recursive descent parser:
+ *
+ * expression := term (('+' | '-') term)*
+ * term       := unary (('*' | '/') unary)*
+ * unary      := ('-' | '+') unary | power
+ * power      := primary (('^' | '**') unary)?
+ * primary    := number | 'x' | constant | function '(' expression ')' | '(' expression ')'
+ *
+ * every nested parenthesis, sign and exponent goes through unary, which bounds the recursion.
+ */
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    // unary calls in progress
+    depth: usize,
+}
+
+// deeper expressions are refused instead of overflowing the stack
+const MAX_DEPTH: usize = 256;
+
+impl Parser {
+    fn skip_whitespace(&mut self) {
+        while self.position < self.chars.len() && self.chars[self.position].is_whitespace() {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.get(self.position).copied()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        match self.peek() {
+            Some(found) if found == c => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(found) => Err(format!(
+                "expected `{}` but found `{}` at position {}",
+                c, found, self.position
+            )),
+            None => Err(format!("expected `{}` but reached the end", c)),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expression, String> {
+        let mut left = self.term()?;
+        loop {
+            match self.peek() {
+                Some('+') => {
+                    self.position += 1;
+                    left = Expression::Add(Box::new(left), Box::new(self.term()?));
+                }
+                Some('-') => {
+                    self.position += 1;
+                    left = Expression::Sub(Box::new(left), Box::new(self.term()?));
+                }
+                _ => return Ok(left),
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Expression, String> {
+        let mut left = self.unary()?;
+        loop {
+            match self.peek() {
+                Some('*') if self.chars.get(self.position + 1) != Some(&'*') => {
+                    self.position += 1;
+                    left = Expression::Mul(Box::new(left), Box::new(self.unary()?));
+                }
+                Some('/') => {
+                    self.position += 1;
+                    left = Expression::Div(Box::new(left), Box::new(self.unary()?));
+                }
+                _ => return Ok(left),
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expression, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err("expression is nested too deeply".to_string());
+        }
+        self.depth += 1;
+        let unary = self.signed();
+        self.depth -= 1;
+        unary
+    }
+
+    fn signed(&mut self) -> Result<Expression, String> {
+        match self.peek() {
+            Some('-') => {
+                self.position += 1;
+                Ok(Expression::Neg(Box::new(self.unary()?)))
+            }
+            Some('+') => {
+                self.position += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<Expression, String> {
+        let base = self.primary()?;
+        match self.peek() {
+            Some('^') => {
+                self.position += 1;
+            }
+            Some('*') if self.chars.get(self.position + 1) == Some(&'*') => {
+                self.position += 2;
+            }
+            _ => return Ok(base),
+        }
+        Ok(Expression::Pow(Box::new(base), Box::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> Result<Expression, String> {
+        match self.peek() {
+            Some('(') => {
+                self.position += 1;
+                let inner = self.expression()?;
+                self.expect(')')?;
+                Ok(inner)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.position;
+                while self.position < self.chars.len()
+                    && self.chars[self.position].is_ascii_alphanumeric()
+                {
+                    self.position += 1;
+                }
+                let name = self.chars[start..self.position]
+                    .iter()
+                    .collect::<String>()
+                    .to_lowercase();
+                match name.as_str() {
+                    "x" => Ok(Expression::Variable),
+                    "pi" => Ok(Expression::Number(std::f64::consts::PI)),
+                    "e" => Ok(Expression::Number(std::f64::consts::E)),
+                    _ => match Function::from_name(&name) {
+                        Some(f) => {
+                            self.expect('(')?;
+                            let argument = self.expression()?;
+                            self.expect(')')?;
+                            Ok(Expression::Function(f, Box::new(argument)))
+                        }
+                        None => Err(format!("unknown name `{}` at position {}", name, start)),
+                    },
+                }
+            }
+            Some(c) => Err(format!("unexpected `{}` at position {}", c, self.position)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn number(&mut self) -> Result<Expression, String> {
+        let start = self.position;
+        while self.position < self.chars.len()
+            && (self.chars[self.position].is_ascii_digit() || self.chars[self.position] == '.')
+        {
+            self.position += 1;
+        }
+        // optional exponent, e.g. 1e-3
+        if self.position < self.chars.len()
+            && (self.chars[self.position] == 'e' || self.chars[self.position] == 'E')
+            && self
+                .chars
+                .get(self.position + 1)
+                .is_some_and(|c| c.is_ascii_digit() || *c == '-' || *c == '+')
+        {
+            self.position += 2;
+            while self.position < self.chars.len() && self.chars[self.position].is_ascii_digit() {
+                self.position += 1;
+            }
+        }
+        let text = self.chars[start..self.position].iter().collect::<String>();
+        text.parse::<f64>()
+            .map(Expression::Number)
+            .map_err(|_| format!("invalid number `{}` at position {}", text, start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_with_precedence() {
+        let e = Expression::parse("1 + 2 * x ^ 2 - -3").unwrap();
+        assert_eq!(e.evaluate(2.), 1. + 2. * 4. + 3.);
+        assert_eq!(Expression::parse("-x**2").unwrap().evaluate(3.), -9.);
+        assert_eq!(Expression::parse("2^3^2").unwrap().evaluate(0.), 512.);
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        assert!(Expression::parse("x / (1 + abs(x)").is_err());
+        assert!(Expression::parse("foo(x)").is_err());
+        assert!(Expression::parse("x x").is_err());
+        assert!(Expression::parse("").is_err());
+
+        let nested = |depth: usize| format!("{}x{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(Expression::parse(&nested(200)).is_ok());
+        assert_eq!(
+            Expression::parse(&nested(5000)).unwrap_err(),
+            "expression is nested too deeply"
+        );
+        assert!(Expression::parse(&"-".repeat(5000)).is_err());
+    }
+
+    #[test]
+    fn symbolic_derivative_matches_finite_difference() {
+        let sources = [
+            "x / (1 + abs(x))",
+            "x * tanh(ln(1 + exp(x)))",
+            "sqrt(x^2 + 1) - 1",
+            "2^x * sin(x) / cosh(x)",
+            "1e-1 * x + exp(-x^2 / 2)",
+        ];
+        let h = 1e-6;
+        for source in sources {
+            let f = Expression::parse(source).unwrap();
+            let df = f.derivative();
+            for x in [-2.3, -0.7, 0.4, 1.9] {
+                let numeric = (f.evaluate(x + h) - f.evaluate(x - h)) / (2. * h);
+                assert!(
+                    (numeric - df.evaluate(x)).abs() < 1e-5,
+                    "{} at {}: {} != {}",
+                    source,
+                    x,
+                    numeric,
+                    df.evaluate(x)
+                );
+            }
+        }
+    }
+}
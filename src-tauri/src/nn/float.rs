@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    iter::Sum,
+    ops::{AddAssign, DivAssign, MulAssign, SubAssign},
+};
+
+/**
+ * Floating point type a network stores its weights and data in.
+ */
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Precision {
+    F32,
+    #[default]
+    F64,
+}
+
+/**
+ * Numeric type of the network computations.
+ *
+ * f64 is the default, f32 halves the memory of the weights and the training data
+ * and vectorizes better. reports and metrics are always computed in f64.
+ */
+pub trait Float:
+    num_traits::Float
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+    + Sum
+    + Default
+    + Debug
+    + Send
+    + Sync
+    + 'static
+{
+    const PRECISION: Precision;
+
+    fn from_f64(value: f64) -> Self;
+
+    fn as_f64(self) -> f64;
+}
+
+impl Float for f32 {
+    const PRECISION: Precision = Precision::F32;
+
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Float for f64 {
+    const PRECISION: Precision = Precision::F64;
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn as_f64(self) -> f64 {
+        self
+    }
+}
@@ -0,0 +1,207 @@
+use std::mem::swap;
+
+use rayon::prelude::*;
+
+use super::{calibration::Calibration, float::Float, matrix::Matrix, NeuralNetwork};
+
+// samples predicted by one rayon task, each task owns a single scratch
+const PREDICT_CHUNK_SIZE: usize = 64;
+
+/**
+ * Reusable buffers for inference, both as wide as the widest layer of the network.
+ *
+ * every layer reads its inputs from front and writes its outputs to back before the two
+ * are swapped, so predict_into does not allocate once the scratch exists.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct Scratch<T = f64> {
+    front: Vec<T>,
+    back: Vec<T>,
+}
+
+impl<T: Float> NeuralNetwork<T> {
+    // buffers for predict_into, reuse them for as many predictions as possible
+    pub fn scratch(&self) -> Scratch<T> {
+        let width = self
+            .layers
+            .iter()
+            .map(|layer| layer.weights.cols().max(layer.neurons_count()))
+            .max()
+            .unwrap_or(0);
+        Scratch {
+            front: vec![T::zero(); width],
+            back: vec![T::zero(); width],
+        }
+    }
+
+    pub fn outputs_count(&self) -> usize {
+        self.layers.last().map_or(0, |layer| layer.neurons_count())
+    }
+
+    /**
+     * Predicts raw (not normalized) inputs into outputs without allocating.
+     *
+     * outputs holds one value per output neuron, calibrated like predict.
+     */
+    pub fn predict_into(&self, inputs: &[f64], scratch: &mut Scratch<T>, outputs: &mut [f64]) {
+        let Scratch { front, back } = scratch;
+        let calibrated = !matches!(self.calibration, Calibration::None);
+
+        let mut width = inputs.len().min(front.len());
+        self.normalize_into(inputs, &mut front[..width]);
+
+        let last = self.layers.len() - 1;
+        for (l, layer) in self.layers.iter().enumerate() {
+            let neurons_count = layer.neurons_count();
+            let (layer_inputs, layer_outputs) = (&front[..width], &mut back[..neurons_count]);
+            // calibration starts from the output logits
+            if calibrated && l == last {
+                layer.logits_into(layer_inputs, layer_outputs);
+            } else {
+                layer.predict_into(layer_inputs, layer_outputs);
+            }
+            swap(front, back);
+            width = neurons_count;
+        }
+
+        outputs
+            .iter_mut()
+            .zip(&front[..width])
+            .for_each(|(o, v)| *o = v.as_f64());
+        if calibrated {
+            self.calibration
+                .apply_in_place(outputs, self.exclusive_outputs());
+        }
+    }
+
+    // the raw inputs scaled like the training data
+    pub fn normalize_into(&self, inputs: &[f64], normalized: &mut [T]) {
+        normalized
+            .iter_mut()
+            .zip(inputs)
+            .enumerate()
+            .for_each(|(i, (v, x))| {
+                *v = match self.normalization_factors.get(i) {
+                    Some(nf) => T::from_f64((*x - nf.0) / (nf.1 - nf.0)),
+                    None => T::from_f64(*x),
+                }
+            });
+    }
+
+    /**
+     * Outputs of every hidden layer for many raw samples, in parallel.
+     *
+     * Returns one (samples × neurons) matrix per hidden layer.
+     */
+    #[allow(dead_code)]
+    pub fn hidden_outputs_many<R: AsRef<[f64]> + Sync>(&self, inputs: &[R]) -> Vec<Matrix<f64>> {
+        let hidden_layers = &self.layers[..self.layers.len() - 1];
+        let chunks = inputs
+            .par_chunks(PREDICT_CHUNK_SIZE)
+            .map(|inputs| {
+                let mut layer_inputs = Matrix::zeros(inputs.len(), self.layers[0].weights.cols());
+                inputs
+                    .iter()
+                    .enumerate()
+                    .for_each(|(b, i)| self.normalize_into(i.as_ref(), layer_inputs.row_mut(b)));
+                hidden_layers
+                    .iter()
+                    .map(|layer| {
+                        layer_inputs = layer.activate(&layer.weighted_sums(&layer_inputs));
+                        layer_inputs.data().iter().map(|v| v.as_f64()).collect()
+                    })
+                    .collect::<Vec<Vec<f64>>>()
+            })
+            .collect::<Vec<_>>();
+
+        hidden_layers
+            .iter()
+            .enumerate()
+            .map(|(l, layer)| {
+                let data = chunks.iter().flat_map(|c| c[l].iter().copied()).collect();
+                Matrix::from_vec(inputs.len(), layer.neurons_count(), data)
+            })
+            .collect()
+    }
+
+    /**
+     * Predicts many raw samples at once, in parallel.
+     *
+     * Returns a (samples × outputs) matrix, every rayon task reuses one scratch.
+     */
+    #[allow(dead_code)]
+    pub fn predict_many<R: AsRef<[f64]> + Sync>(&self, inputs: &[R]) -> Matrix<f64> {
+        let outputs_count = self.outputs_count();
+        let mut outputs = Matrix::zeros(inputs.len(), outputs_count);
+        if outputs_count == 0 {
+            return outputs;
+        }
+
+        outputs
+            .data_mut()
+            .par_chunks_mut(outputs_count * PREDICT_CHUNK_SIZE)
+            .zip(inputs.par_chunks(PREDICT_CHUNK_SIZE))
+            .for_each_init(
+                || self.scratch(),
+                |scratch, (outputs, inputs)| {
+                    outputs
+                        .chunks_mut(outputs_count)
+                        .zip(inputs)
+                        .for_each(|(o, i)| self.predict_into(i.as_ref(), scratch, o));
+                },
+            );
+        outputs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::activation_functions::ActivationFunction;
+    use super::*;
+
+    #[test]
+    fn predict_many_matches_predict() {
+        let data = (0..60)
+            .map(|i| {
+                let class = i % 3;
+                (
+                    vec![i as f64 % 7., i as f64 / 3.],
+                    (0..3).map(|c| (c == class) as u8 as f64).collect(),
+                )
+            })
+            .collect::<Vec<(Vec<f64>, Vec<f64>)>>();
+        let mut nn: NeuralNetwork = NeuralNetwork::with_seed(
+            Some(3),
+            2,
+            vec![6, 5],
+            &[ActivationFunction::Relu, ActivationFunction::Tanh],
+            3,
+            ActivationFunction::Softmax,
+            0.1,
+            data.clone(),
+            vec![],
+            vec![],
+        );
+        nn.epoch();
+
+        let inputs = data.iter().map(|(x, _)| x.as_slice()).collect::<Vec<_>>();
+        let outputs = nn.predict_many(&inputs);
+        assert_eq!((outputs.rows(), outputs.cols()), (data.len(), 3));
+        for (i, (x, _)) in data.iter().enumerate() {
+            assert_eq!(outputs.row(i), nn.predict(x).as_slice());
+        }
+
+        // the last hidden layer feeds the output layer
+        let hidden = nn.hidden_outputs_many(&inputs);
+        assert_eq!(hidden.len(), 2);
+        assert_eq!((hidden[1].rows(), hidden[1].cols()), (data.len(), 5));
+        let output_layer = nn.layers.last().unwrap();
+        for i in 0..data.len() {
+            let expected = output_layer.predict(hidden[1].row(i));
+            expected
+                .iter()
+                .zip(outputs.row(i))
+                .for_each(|(a, b)| assert!((a - b).abs() < 1e-12));
+        }
+    }
+}
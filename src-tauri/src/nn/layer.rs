@@ -1,9 +1,16 @@
-use rayon::prelude::*;
+use rand::{
+    distributions::{Distribution, Uniform},
+    Rng,
+};
 use serde::{Deserialize, Serialize};
 
 use super::{
-    activation_functions::{get_activation_function, ActivationFunction},
-    neuron::{GradiantErrorInput, Neuron},
+    activation_functions::{
+        get_activation_function, softmax_in_place, ActivationFunction, ActivationFunctionWrapper,
+        ActivationParameters, PreluSharing,
+    },
+    float::Float,
+    matrix::{dot, Matrix},
 };
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
@@ -15,130 +22,486 @@ pub enum LayerType {
     Error,
 }
 
+/**
+ * Loss minimized by the output layer.
+ *
+ * BinaryCrossEntropy assumes sigmoid outputs so the activation derivative cancels out.
+ */
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Loss {
+    MeanSquaredError,
+    BinaryCrossEntropy,
+}
+
+/**
+ * Gradients of one layer summed (or averaged) over a set of samples.
+ *
+ * workers compute them independently for their samples, the network adds them up
+ * before a single update.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct LayerGradients<T = f64> {
+    // ΔW direction (neurons × inputs)
+    pub weights: Matrix<T>,
+    // Δ⍬ direction is the negation of these
+    pub thresholds: Vec<T>,
+    // learnable activation parameter of each neuron (PRELU)
+    pub parameters: Vec<T>,
+}
+
+impl<T: Float> LayerGradients<T> {
+    pub fn add(&mut self, other: &LayerGradients<T>) {
+        self.weights.add_scaled(T::one(), &other.weights);
+        self.thresholds
+            .iter_mut()
+            .zip(&other.thresholds)
+            .for_each(|(a, &b)| *a += b);
+        self.parameters
+            .iter_mut()
+            .zip(&other.parameters)
+            .for_each(|(a, &b)| *a += b);
+    }
+
+    pub fn scale(&mut self, a: T) {
+        self.weights.scale(a);
+        self.thresholds.iter_mut().for_each(|g| *g *= a);
+        self.parameters.iter_mut().for_each(|g| *g *= a);
+    }
+
+    pub fn norm_squared(&self) -> f64 {
+        self.weights.norm_squared()
+            + self
+                .thresholds
+                .iter()
+                .chain(&self.parameters)
+                .map(|g| g.as_f64().powi(2))
+                .sum::<f64>()
+    }
+}
+
+/**
+ * Dense layer stored as a (neurons × inputs) weight matrix and a threshold vector.
+ *
+ * forward / backward work on a whole batch of samples (one row per sample),
+ * commit applies the gradients averaged over that batch.
+ * the pure building blocks (weighted_sums, activate, error_signals, gradients)
+ * never touch the layer so several workers can share it.
+ */
 #[derive(Clone, Debug)]
-pub struct Layer {
+pub struct Layer<T: Float = f64> {
     #[allow(dead_code)]
     pub layer_id: usize,
     pub layer_type: LayerType,
-    pub neurons: Vec<Neuron>,
+    // row j holds the input weights of neuron j
+    pub weights: Matrix<T>,
+    // X = SUM(Xi*Wi) - THRESHOLD
+    pub thresholds: Vec<T>,
+    // one per neuron so learnable parameters (PRELU) can differ between neurons
+    activation_functions: Vec<ActivationFunctionWrapper<T>>,
     pub activation_function: ActivationFunction,
-    pub current_inputs: Vec<f64>,
-    pub current_outputs: Vec<f64>,
+    pub activation_parameters: ActivationParameters,
+    pub loss: Loss,
+    // learning rate
+    pub alpha: T,
+    // (samples × inputs) of the last forward step
+    pub current_inputs: Matrix<T>,
+    // (samples × neurons) weighted sums before the activation function
+    pub current_x: Matrix<T>,
+    // (samples × neurons) used as inputs for the next layer
+    pub current_outputs: Matrix<T>,
+    // (samples × neurons) error signal of every neuron
+    pub gradiant_errors: Matrix<T>,
+    // averaged over the batch by backward, applied by commit
+    pub gradients: LayerGradients<T>,
 }
 
-impl Layer {
+impl<T: Float> Layer<T> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         layer_id: usize,
         layer_type: LayerType,
         inputs_count: usize,
         neurons_count: usize,
         activation_function: ActivationFunction,
+        activation_parameters: ActivationParameters,
         alpha: f64,
+        rng: &mut impl Rng,
     ) -> Self {
-        let neurons = (0..neurons_count)
-            .map(|_| {
-                Neuron::new(
-                    layer_type.clone(),
-                    inputs_count,
-                    get_activation_function(activation_function),
-                    alpha,
-                )
-            })
+        let step = Uniform::new(-2.4 / inputs_count as f64, 2.4 / inputs_count as f64);
+
+        let weights = Matrix::from_vec(
+            neurons_count,
+            inputs_count,
+            (0..neurons_count * inputs_count)
+                .map(|_| T::from_f64(step.sample(rng)))
+                .collect(),
+        );
+        let thresholds = (0..neurons_count)
+            .map(|_| T::from_f64(step.sample(rng)))
+            .collect::<Vec<_>>();
+        let activation_functions = (0..neurons_count)
+            .map(|_| get_activation_function(&activation_function, &activation_parameters))
             .collect::<Vec<_>>();
 
         Layer {
             layer_id,
             layer_type,
-            neurons: neurons,
+            weights,
+            thresholds,
+            activation_functions,
             activation_function,
-            current_inputs: vec![],
-            current_outputs: vec![],
+            activation_parameters,
+            loss: Loss::MeanSquaredError,
+            alpha: T::from_f64(alpha),
+            current_inputs: Matrix::default(),
+            current_x: Matrix::default(),
+            current_outputs: Matrix::default(),
+            gradiant_errors: Matrix::default(),
+            gradients: LayerGradients {
+                weights: Matrix::zeros(neurons_count, inputs_count),
+                thresholds: vec![T::zero(); neurons_count],
+                parameters: vec![T::zero(); neurons_count],
+            },
         }
     }
 
+    pub fn neurons_count(&self) -> usize {
+        self.thresholds.len()
+    }
+
     /**
-     * return y_actual for all neurons in this layer.
+     * computes y_actual of all neurons for every sample of the batch.
      *
-     * will be used as inputs for the next layer.
+     * the result is stored in current_outputs and used as inputs for the next layer.
      */
-    pub fn forward(&mut self, inputs: &Vec<f64>) -> Vec<f64> {
+    pub fn forward(&mut self, inputs: &Matrix<T>) {
         // needed for backpropagation
         self.current_inputs = inputs.clone();
-        self.current_outputs = self
-            .neurons
-            .par_iter_mut()
-            .map(|n| n.forward(inputs))
-            .collect::<Vec<_>>();
+        self.current_x = self.weighted_sums(inputs);
+        self.current_outputs = self.activate(&self.current_x);
+    }
+
+    /**
+     * compute gradiant error for each weight and store it
+     * (DO NOT UPDATE WEIGHTS)
+     *
+     * y_desired is only read by the output layer, hidden layers use the next layer errors.
+     */
+    pub fn backward(&mut self, y_desired: &Matrix<T>, next_layer: Option<&Layer<T>>) {
+        let errors = match (self.layer_type, next_layer) {
+            (LayerType::Output, _) => output_errors(y_desired, &self.current_outputs),
+            (LayerType::Hidden, Some(next_layer)) => next_layer.input_errors(),
+            _ => Matrix::zeros(self.current_x.rows(), self.neurons_count()),
+        };
+        let (gradiant_errors, parameters) = self.error_signals(&self.current_x, &errors);
+        self.gradients = self.gradients(&self.current_inputs, &gradiant_errors, parameters);
+        self.gradients
+            .scale(T::one() / T::from_f64(self.current_x.rows().max(1) as f64));
+        self.gradiant_errors = gradiant_errors;
+    }
+
+    pub fn commit(&mut self) {
+        let gradients = std::mem::take(&mut self.gradients);
+        self.apply(&gradients);
+        self.gradients = gradients;
+    }
+
+    /**
+     * W + α * ΔW, ⍬ + α * Δ⍬ and the learnable activation parameters.
+     */
+    pub fn apply(&mut self, gradients: &LayerGradients<T>) {
+        self.weights.add_scaled(self.alpha, &gradients.weights);
+        self.thresholds
+            .iter_mut()
+            .zip(&gradients.thresholds)
+            .for_each(|(t, &g)| *t -= self.alpha * g);
+
+        // learnable activation parameters (PRELU)
+        let alpha = self.alpha;
+        let commit_parameter = |f: &mut ActivationFunctionWrapper<T>, gradiant: T| {
+            if let Some(parameter) = f.parameter() {
+                f.set_parameter(parameter + alpha * gradiant);
+            }
+        };
+        match self.activation_parameters.prelu_sharing {
+            PreluSharing::PerNeuron => self
+                .activation_functions
+                .iter_mut()
+                .zip(&gradients.parameters)
+                .for_each(|(f, &g)| commit_parameter(f, g)),
+            PreluSharing::PerLayer => {
+                let shared = gradients.parameters.iter().copied().sum::<T>()
+                    / T::from_f64(gradients.parameters.len().max(1) as f64);
+                self.activation_functions
+                    .iter_mut()
+                    .for_each(|f| commit_parameter(f, shared));
+            }
+        }
+    }
+
+    /**
+     * (samples × neurons) SUM(Xi*Wi) - THRESHOLD
+     */
+    pub fn weighted_sums(&self, inputs: &Matrix<T>) -> Matrix<T> {
+        let mut x = inputs.mul_transposed(&self.weights);
+        for b in 0..x.rows() {
+            x.row_mut(b)
+                .iter_mut()
+                .zip(&self.thresholds)
+                .for_each(|(x, &threshold)| *x -= threshold);
+        }
+        x
+    }
+
+    /**
+     * (samples × neurons) ACTIVATION_FUNCTION(x)
+     */
+    pub fn activate(&self, x: &Matrix<T>) -> Matrix<T> {
+        let mut outputs = x.clone();
+        for b in 0..outputs.rows() {
+            self.activate_in_place(outputs.row_mut(b));
+        }
+        outputs
+    }
+
+    /**
+     * replaces the weighted sums of one sample by the outputs of the neurons.
+     */
+    pub fn activate_in_place(&self, values: &mut [T]) {
+        values
+            .iter_mut()
+            .zip(&self.activation_functions)
+            .for_each(|(v, f)| *v = f.apply(*v));
 
         // NEEDED FOR SOFTMAX ACTIVATION FUNCTION
-        match self.activation_function {
-            ActivationFunction::Softmax => self
-                .neurons
-                .par_iter_mut()
-                .for_each(|n| n.commit_activation_function(&self.current_outputs)),
-            _ => (),
+        if let ActivationFunction::Softmax = self.activation_function {
+            softmax_in_place(values);
+        }
+    }
+
+    /**
+     * error signal δ of every neuron from the errors of its outputs.
+     *
+     * also returns the activation parameter gradients summed over the samples.
+     */
+    pub fn error_signals(&self, x: &Matrix<T>, errors: &Matrix<T>) -> (Matrix<T>, Vec<T>) {
+        let neurons = self.neurons_count();
+        let mut gradiant_errors = Matrix::zeros(x.rows(), neurons);
+        let mut parameters = vec![T::zero(); neurons];
+        for b in 0..x.rows() {
+            // IMPORTANT FOR SOFTMAX ACTIVATION FUNCTION: derivatives see X, not Y
+            let x = x.row(b).to_vec();
+            let (errors, gradiant_errors) = (errors.row(b), gradiant_errors.row_mut(b));
+            for j in 0..neurons {
+                let f = &self.activation_functions[j];
+                let derivative = match (self.layer_type, self.loss) {
+                    // d(BCE)/dx = y - y_desired for sigmoid outputs
+                    (LayerType::Output, Loss::BinaryCrossEntropy) => T::one(),
+                    _ => f.derivative(x[j], &x),
+                };
+                gradiant_errors[j] = derivative * errors[j];
+                parameters[j] += f.parameter_derivative(x[j]) * errors[j];
+            }
         }
+        (gradiant_errors, parameters)
+    }
 
-        // needed as inputs for the next layer
-        self.current_outputs.clone()
+    /**
+     * ΔW = δᵀ · inputs and Δ⍬ = -δ summed over the samples.
+     */
+    pub fn gradients(
+        &self,
+        inputs: &Matrix<T>,
+        gradiant_errors: &Matrix<T>,
+        parameters: Vec<T>,
+    ) -> LayerGradients<T> {
+        let mut thresholds = vec![T::zero(); self.neurons_count()];
+        gradiant_errors
+            .iter_rows()
+            .for_each(|row| thresholds.iter_mut().zip(row).for_each(|(g, &d)| *g += d));
+        LayerGradients {
+            weights: gradiant_errors.transposed_mul(inputs),
+            thresholds,
+            parameters,
+        }
     }
 
-    // compute gradiant error for each weight and store it
-    // (DO NOT UPDATE WEIGHTS)
-    pub fn backward(&mut self, y_desired: &Vec<f64>, next_layer: Option<&Layer>) {
-        // move to neuron.
+    // errors of the previous layer outputs: δ · W
+    pub fn input_errors(&self) -> Matrix<T> {
+        self.gradiant_errors.mul(&self.weights)
+    }
 
-        // IMPORTANT FOR SOFTMAX ACTIVATION FUNCTION
-        // IT WAS Y. NOW IT IS X (2024-11-02)
-        // let outputs = self.neurons.iter().map(|n: &Neuron| n.x).collect::<Vec<_>>();
+    /**
+     * (neurons × neurons) ∂y/∂x of one sample.
+     *
+     * only softmax outputs depend on the other weighted sums, every other activation is diagonal.
+     */
+    pub fn jacobian(&self, x: &[T]) -> Matrix<T> {
+        let neurons = self.neurons_count();
+        let mut jacobian = Matrix::zeros(neurons, neurons);
+        if let ActivationFunction::Softmax = self.activation_function {
+            let mut y = x.to_vec();
+            softmax_in_place(&mut y);
+            for k in 0..neurons {
+                jacobian
+                    .row_mut(k)
+                    .iter_mut()
+                    .zip(&y)
+                    .enumerate()
+                    .for_each(|(j, (d, &y_j))| {
+                        let kronecker = if j == k { T::one() } else { T::zero() };
+                        *d = y[k] * (kronecker - y_j);
+                    });
+            }
+        } else {
+            for k in 0..neurons {
+                jacobian.row_mut(k)[k] = self.activation_functions[k].derivative(x[k], x);
+            }
+        }
+        jacobian
+    }
 
-        self.neurons.par_iter_mut().enumerate().for_each(|(i, n)| {
-            let input = match (self.layer_type, &y_desired, next_layer) {
-                (LayerType::Hidden, _, Some(next_layer)) => {
-                    GradiantErrorInput::NextLayer(next_layer)
-                }
-                (LayerType::Output, y_desired, _) => GradiantErrorInput::YDesired(y_desired[i]),
-                _ => GradiantErrorInput::Error,
-            };
-            n.backward(input, i, &self.current_outputs);
-        });
+    pub fn set_activation_function(&mut self, activation_function: ActivationFunction) {
+        self.activation_function = activation_function;
+        self.set_activation_parameters(self.activation_parameters);
     }
 
-    pub fn commit(&mut self) {
-        self.neurons
-            .par_iter_mut()
-            .for_each(|n| n.commit(&self.current_inputs));
+    /**
+     * Replaces the activation function of every neuron using the given parameters.
+     *
+     * learned parameters are reset to their initial values.
+     */
+    pub fn set_activation_parameters(&mut self, activation_parameters: ActivationParameters) {
+        self.activation_parameters = activation_parameters;
+        self.activation_functions = (0..self.neurons_count())
+            .map(|_| get_activation_function(&self.activation_function, &activation_parameters))
+            .collect();
     }
 
     #[allow(dead_code)]
-    pub fn predict(&self, inputs: &Vec<f64>) -> Vec<f64> {
-        let outputs = self
-            .neurons
-            .iter()
-            .map(|n| n.predict(inputs))
-            .collect::<Vec<_>>();
+    pub fn predict(&self, inputs: &[T]) -> Vec<T> {
+        let mut outputs = vec![T::zero(); self.neurons_count()];
+        self.predict_into(inputs, &mut outputs);
+        outputs
+    }
 
-        // NEEDED FOR SOFTMAX ACTIVATION FUNCTION
-        match self.activation_function {
-            ActivationFunction::Softmax => outputs
-                .iter()
-                .map(|&o| get_activation_function(self.activation_function).commit(o, &outputs))
-                .collect::<Vec<_>>(),
-            _ => outputs,
-            // ActivationFunction::Softmax => self
-            //     .neurons
-            //     .iter()
-            //     .map(|n| get_activation_function(self.activation_function).commit(n.y, &outputs))
-            //     .collect::<Vec<_>>(),
-            // _ => outputs,
-        }
+    /**
+     * outputs of one sample written into outputs (one value per neuron), without allocating.
+     */
+    pub fn predict_into(&self, inputs: &[T], outputs: &mut [T]) {
+        self.logits_into(inputs, outputs);
+        self.activate_in_place(outputs);
+    }
+
+    /**
+     * squared L2 norm of the weight and threshold gradients of the last backward step.
+     */
+    pub fn gradient_norm_squared(&self) -> f64 {
+        self.gradients.norm_squared()
+    }
+
+    // weighted sums of the neurons before the activation function
+    pub fn predict_logits(&self, inputs: &[T]) -> Vec<T> {
+        let mut outputs = vec![T::zero(); self.neurons_count()];
+        self.logits_into(inputs, &mut outputs);
+        outputs
+    }
+
+    pub fn logits_into(&self, inputs: &[T], outputs: &mut [T]) {
+        outputs
+            .iter_mut()
+            .zip(self.weights.iter_rows().zip(&self.thresholds))
+            .for_each(|(o, (w, &threshold))| *o = dot(w, inputs) - threshold);
     }
 
     pub fn get_parameters(&self) -> Vec<(Vec<f64>, f64, LayerType)> {
-        self.neurons
-            .iter()
-            .map(|n| n.get_parameters())
-            .map(|(weights, threshold)| (weights, threshold, self.layer_type))
+        self.weights
+            .iter_rows()
+            .zip(&self.thresholds)
+            .map(|(weights, &threshold)| {
+                (
+                    weights.iter().map(|w| w.as_f64()).collect(),
+                    threshold.as_f64(),
+                    self.layer_type,
+                )
+            })
             .collect::<Vec<_>>()
     }
+
+    // restores learned activation parameters (PRELU), None leaves a neuron unchanged
+    pub fn set_learned_parameters(&mut self, parameters: &[Option<f64>]) {
+        self.activation_functions
+            .iter_mut()
+            .zip(parameters)
+            .for_each(|(f, p)| {
+                if let Some(p) = p {
+                    f.set_parameter(T::from_f64(*p));
+                }
+            });
+    }
+
+    // current value of the learnable activation parameter of each neuron
+    #[allow(dead_code)]
+    pub fn get_activation_parameters(&self) -> Vec<Option<f64>> {
+        self.activation_functions
+            .iter()
+            .map(|f| f.parameter().map(|p| p.as_f64()))
+            .collect()
+    }
+}
+
+// y_desired - y_actual
+pub fn output_errors<T: Float>(y_desired: &Matrix<T>, outputs: &Matrix<T>) -> Matrix<T> {
+    let mut errors = y_desired.clone();
+    errors.add_scaled(-T::one(), outputs);
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_gradients_are_averaged_single_sample_gradients() {
+        let mut layer = Layer::<f64>::new(
+            1,
+            LayerType::Output,
+            3,
+            2,
+            ActivationFunction::Sigmoid,
+            ActivationParameters::default(),
+            0.1,
+            &mut rand::thread_rng(),
+        );
+        let inputs = Matrix::from_rows(&[[0.1, 0.5, -0.3], [0.9, -0.2, 0.4]]);
+        let y_desired = Matrix::from_rows(&[[1., 0.], [0., 1.]]);
+
+        let mut expected = Matrix::zeros(2, 3);
+        for b in 0..2 {
+            layer.forward(&Matrix::from_rows(&[inputs.row(b)]));
+            layer.backward(&Matrix::from_rows(&[y_desired.row(b)]), None);
+            expected.add_scaled(0.5, &layer.gradients.weights);
+        }
+
+        layer.forward(&inputs);
+        layer.backward(&y_desired, None);
+        layer
+            .gradients
+            .weights
+            .data()
+            .iter()
+            .zip(expected.data())
+            .for_each(|(a, b)| assert!((a - b).abs() < 1e-12));
+    }
+
+    #[test]
+    fn gradient_norm_counts_the_prelu_parameters() {
+        let gradients = LayerGradients {
+            weights: Matrix::from_rows(&[[1., 2.]]),
+            thresholds: vec![2.],
+            parameters: vec![4.],
+        };
+        assert_eq!(gradients.norm_squared(), 25.);
+    }
 }
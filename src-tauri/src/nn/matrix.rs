@@ -0,0 +1,169 @@
+use super::float::Float;
+
+/**
+ * Dense row-major matrix.
+ *
+ * layers keep their weights as (neurons × inputs) and batches as (samples × features),
+ * so every product below walks contiguous rows.
+ */
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Matrix<T = f64> {
+    rows: usize,
+    cols: usize,
+    data: Vec<T>,
+}
+
+impl<T: Float> Matrix<T> {
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        Matrix {
+            rows,
+            cols,
+            data: vec![T::zero(); rows * cols],
+        }
+    }
+
+    pub fn from_vec(rows: usize, cols: usize, data: Vec<T>) -> Self {
+        assert_eq!(
+            data.len(),
+            rows * cols,
+            "matrix data does not match its shape"
+        );
+        Matrix { rows, cols, data }
+    }
+
+    pub fn from_rows<R: AsRef<[T]>>(rows: &[R]) -> Self {
+        let cols = rows.first().map_or(0, |r| r.as_ref().len());
+        let data = rows
+            .iter()
+            .flat_map(|r| r.as_ref().iter().copied())
+            .collect::<Vec<_>>();
+        Matrix::from_vec(rows.len(), cols, data)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+
+    pub fn row(&self, i: usize) -> &[T] {
+        &self.data[i * self.cols..(i + 1) * self.cols]
+    }
+
+    pub fn row_mut(&mut self, i: usize) -> &mut [T] {
+        &mut self.data[i * self.cols..(i + 1) * self.cols]
+    }
+
+    pub fn iter_rows(&self) -> impl Iterator<Item = &[T]> {
+        (0..self.rows).map(move |i| self.row(i))
+    }
+
+    pub fn to_rows(&self) -> Vec<Vec<T>> {
+        self.iter_rows().map(|r| r.to_vec()).collect()
+    }
+
+    /**
+     * self · otherᵀ
+     */
+    pub fn mul_transposed(&self, other: &Matrix<T>) -> Matrix<T> {
+        assert_eq!(self.cols, other.cols, "matrix shapes do not match");
+        let mut result = Matrix::zeros(self.rows, other.rows);
+        for i in 0..self.rows {
+            let a = self.row(i);
+            let out = result.row_mut(i);
+            for (j, o) in out.iter_mut().enumerate() {
+                *o = dot(a, other.row(j));
+            }
+        }
+        result
+    }
+
+    /**
+     * self · other
+     */
+    pub fn mul(&self, other: &Matrix<T>) -> Matrix<T> {
+        assert_eq!(self.cols, other.rows, "matrix shapes do not match");
+        let mut result = Matrix::zeros(self.rows, other.cols);
+        for i in 0..self.rows {
+            let out = &mut result.data[i * other.cols..(i + 1) * other.cols];
+            for (k, &a) in self.row(i).iter().enumerate() {
+                if a != T::zero() {
+                    axpy(a, other.row(k), out);
+                }
+            }
+        }
+        result
+    }
+
+    /**
+     * selfᵀ · other
+     */
+    pub fn transposed_mul(&self, other: &Matrix<T>) -> Matrix<T> {
+        assert_eq!(self.rows, other.rows, "matrix shapes do not match");
+        let mut result = Matrix::zeros(self.cols, other.cols);
+        for k in 0..self.rows {
+            let b = other.row(k);
+            for (i, &a) in self.row(k).iter().enumerate() {
+                if a != T::zero() {
+                    axpy(a, b, result.row_mut(i));
+                }
+            }
+        }
+        result
+    }
+
+    // self += a * other
+    pub fn add_scaled(&mut self, a: T, other: &Matrix<T>) {
+        assert_eq!(
+            (self.rows, self.cols),
+            (other.rows, other.cols),
+            "matrix shapes do not match"
+        );
+        axpy(a, &other.data, &mut self.data);
+    }
+
+    pub fn scale(&mut self, a: T) {
+        self.data.iter_mut().for_each(|v| *v *= a);
+    }
+
+    pub fn norm_squared(&self) -> f64 {
+        self.data.iter().map(|v| v.as_f64().powi(2)).sum()
+    }
+}
+
+pub fn dot<T: Float>(a: &[T], b: &[T]) -> T {
+    a.iter().zip(b).map(|(&x, &y)| x * y).sum()
+}
+
+// y += a * x
+pub fn axpy<T: Float>(a: T, x: &[T], y: &mut [T]) {
+    y.iter_mut().zip(x).for_each(|(y, &x)| *y += a * x);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn products() {
+        let a = Matrix::from_rows(&[[1., 2.], [3., 4.], [5., 6.]]);
+        let b = Matrix::from_rows(&[[1., 0.], [0., 2.]]);
+        assert_eq!(a.mul(&b).to_rows(), vec![[1., 4.], [3., 8.], [5., 12.]]);
+        assert_eq!(
+            a.mul_transposed(&a).row(0),
+            &[5., 11., 17.],
+            "row 0 of a · aᵀ"
+        );
+        assert_eq!(a.transposed_mul(&a).to_rows(), vec![[35., 44.], [44., 56.]]);
+    }
+}
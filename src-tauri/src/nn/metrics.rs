@@ -0,0 +1,361 @@
+use serde::{Deserialize, Serialize};
+
+use super::Split;
+
+/**
+ * Metrics that can be recorded after every epoch.
+ */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Metric {
+    TrainingAccuracy,
+    ValidationAccuracy,
+    TrainingCrossEntropy,
+    ValidationCrossEntropy,
+    // macro averaged F1
+    TrainingF1,
+    ValidationF1,
+    LearningRate,
+    GradientNorm,
+}
+
+/**
+ * Standard single label classification metrics computed from a confusion matrix.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluationReport {
+    pub split: Split,
+    pub samples: usize,
+    pub accuracy: f64,
+    // mean recall over the classes present in the data
+    pub balanced_accuracy: f64,
+    pub classes: Vec<ClassMetrics>,
+    pub macro_average: AverageMetrics,
+    // averaged using the support of each class
+    pub weighted_average: AverageMetrics,
+    pub cohen_kappa: f64,
+    // Matthews correlation coefficient (multiclass R_K statistic)
+    pub mcc: f64,
+    pub confusion_matrix: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassMetrics {
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+    // number of samples of this class
+    pub support: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AverageMetrics {
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+}
+
+impl EvaluationReport {
+    /**
+     * confusion_matrix rows are the desired classes, columns the predicted ones.
+     */
+    pub fn new(split: Split, confusion_matrix: Vec<Vec<usize>>) -> Self {
+        let k = confusion_matrix.len();
+        let actual = confusion_matrix
+            .iter()
+            .map(|row| row.iter().sum::<usize>())
+            .collect::<Vec<_>>();
+        let predicted = (0..k)
+            .map(|j| confusion_matrix.iter().map(|row| row[j]).sum::<usize>())
+            .collect::<Vec<_>>();
+        let correct = (0..k).map(|i| confusion_matrix[i][i]).sum::<usize>();
+        let samples = actual.iter().sum::<usize>();
+        let n = samples as f64;
+
+        let classes = (0..k)
+            .map(|i| {
+                let tp = confusion_matrix[i][i];
+                ClassMetrics {
+                    precision: ratio(tp, predicted[i]),
+                    recall: ratio(tp, actual[i]),
+                    f1: f1_score(tp, predicted[i] - tp, actual[i] - tp),
+                    support: actual[i],
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let present = classes.iter().filter(|c| c.support > 0).count().max(1) as f64;
+        let balanced_accuracy = classes
+            .iter()
+            .filter(|c| c.support > 0)
+            .map(|c| c.recall)
+            .sum::<f64>()
+            / present;
+
+        let average = |weight: &dyn Fn(&ClassMetrics) -> f64| {
+            let total = classes.iter().map(weight).sum::<f64>();
+            let mean = |metric: fn(&ClassMetrics) -> f64| {
+                if total == 0.0 {
+                    0.0
+                } else {
+                    classes.iter().map(|c| weight(c) * metric(c)).sum::<f64>() / total
+                }
+            };
+            AverageMetrics {
+                precision: mean(|c| c.precision),
+                recall: mean(|c| c.recall),
+                f1: mean(|c| c.f1),
+            }
+        };
+        let macro_average = average(&|_| 1.0);
+        let weighted_average = average(&|c| c.support as f64);
+
+        // agreement expected by chance
+        let p_o = ratio(correct, samples);
+        let p_e = if samples == 0 {
+            0.0
+        } else {
+            (0..k)
+                .map(|i| actual[i] as f64 * predicted[i] as f64)
+                .sum::<f64>()
+                / (n * n)
+        };
+        let cohen_kappa = if p_e < 1.0 {
+            (p_o - p_e) / (1.0 - p_e)
+        } else {
+            0.0
+        };
+
+        let c = correct as f64;
+        let sum_pt = (0..k)
+            .map(|i| predicted[i] as f64 * actual[i] as f64)
+            .sum::<f64>();
+        let sum_p2 = predicted.iter().map(|&p| (p as f64).powi(2)).sum::<f64>();
+        let sum_t2 = actual.iter().map(|&t| (t as f64).powi(2)).sum::<f64>();
+        let denominator = ((n * n - sum_p2) * (n * n - sum_t2)).sqrt();
+        let mcc = if denominator > 0.0 {
+            (c * n - sum_pt) / denominator
+        } else {
+            0.0
+        };
+
+        EvaluationReport {
+            split,
+            samples,
+            accuracy: p_o,
+            balanced_accuracy,
+            classes,
+            macro_average,
+            weighted_average,
+            cohen_kappa,
+            mcc,
+            confusion_matrix,
+        }
+    }
+}
+
+/**
+ * Evaluation of a multi-label classifier where every row can have any number of active labels.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultiLabelReport {
+    // one [[TN, FP], [FN, TP]] matrix per label
+    pub confusion_matrices: Vec<[[usize; 2]; 2]>,
+    // fraction of wrongly predicted labels
+    pub hamming_loss: f64,
+    // fraction of rows where every label is predicted correctly
+    pub subset_accuracy: f64,
+    pub f1: Vec<f64>,
+    pub micro_f1: f64,
+    pub macro_f1: f64,
+}
+
+impl MultiLabelReport {
+    pub fn new(predicted: &[Vec<bool>], desired: &[Vec<bool>]) -> Self {
+        let labels_count = desired.first().map_or(0, |row| row.len());
+        let mut confusion_matrices = vec![[[0; 2]; 2]; labels_count];
+        let mut wrong_labels = 0;
+        let mut exact_rows = 0;
+
+        predicted.iter().zip(desired).for_each(|(p, d)| {
+            let mut exact = true;
+            p.iter().zip(d).enumerate().for_each(|(j, (&p, &d))| {
+                confusion_matrices[j][d as usize][p as usize] += 1;
+                if p != d {
+                    wrong_labels += 1;
+                    exact = false;
+                }
+            });
+            if exact {
+                exact_rows += 1;
+            }
+        });
+
+        let rows = predicted.len().max(1) as f64;
+        let f1 = confusion_matrices
+            .iter()
+            .map(|m| f1_score(m[1][1], m[0][1], m[1][0]))
+            .collect::<Vec<_>>();
+        let (tp, fp, fn_) = confusion_matrices
+            .iter()
+            .fold((0, 0, 0), |(tp, fp, fn_), m| {
+                (tp + m[1][1], fp + m[0][1], fn_ + m[1][0])
+            });
+
+        MultiLabelReport {
+            hamming_loss: wrong_labels as f64 / (rows * labels_count.max(1) as f64),
+            subset_accuracy: exact_rows as f64 / rows,
+            micro_f1: f1_score(tp, fp, fn_),
+            macro_f1: f1.iter().sum::<f64>() / f1.len().max(1) as f64,
+            f1,
+            confusion_matrices,
+        }
+    }
+}
+
+/**
+ * Evaluation of a regression model with continuous targets.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegressionReport {
+    pub mae: f64,
+    pub rmse: f64,
+    // coefficient of determination averaged over the outputs
+    pub r2: f64,
+    pub residuals: ResidualStatistics,
+}
+
+// statistics of y_desired - y_actual over all outputs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResidualStatistics {
+    pub mean: f64,
+    pub std: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl RegressionReport {
+    pub fn new(predicted: &[Vec<f64>], desired: &[Vec<f64>]) -> Self {
+        let residuals = predicted
+            .iter()
+            .zip(desired)
+            .flat_map(|(p, d)| p.iter().zip(d).map(|(p, d)| d - p))
+            .collect::<Vec<_>>();
+        let n = residuals.len().max(1) as f64;
+
+        let mean = residuals.iter().sum::<f64>() / n;
+        let std = (residuals.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n).sqrt();
+
+        // R² = 1 - SS_res / SS_tot for each output
+        let outputs_count = desired.first().map_or(0, |row| row.len());
+        let r2 = (0..outputs_count)
+            .map(|j| {
+                let column_mean =
+                    desired.iter().map(|d| d[j]).sum::<f64>() / desired.len().max(1) as f64;
+                let ss_tot = desired
+                    .iter()
+                    .map(|d| (d[j] - column_mean).powi(2))
+                    .sum::<f64>();
+                let ss_res = predicted
+                    .iter()
+                    .zip(desired)
+                    .map(|(p, d)| (d[j] - p[j]).powi(2))
+                    .sum::<f64>();
+                match (ss_res, ss_tot) {
+                    (_, t) if t > 0.0 => 1.0 - ss_res / ss_tot,
+                    (0.0, _) => 1.0,
+                    _ => 0.0,
+                }
+            })
+            .sum::<f64>()
+            / outputs_count.max(1) as f64;
+
+        RegressionReport {
+            mae: residuals.iter().map(|r| r.abs()).sum::<f64>() / n,
+            rmse: (residuals.iter().map(|r| r * r).sum::<f64>() / n).sqrt(),
+            r2,
+            residuals: ResidualStatistics {
+                mean,
+                std,
+                min: residuals.iter().cloned().fold(f64::INFINITY, f64::min),
+                max: residuals.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            },
+        }
+    }
+}
+
+// a / b, defined as 0 when b is 0
+pub(super) fn ratio(a: usize, b: usize) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
+}
+
+/**
+ * 2TP / (2TP + FP + FN), defined as 0 when there are no positives at all.
+ */
+pub fn f1_score(tp: usize, fp: usize, fn_: usize) -> f64 {
+    let denominator = 2 * tp + fp + fn_;
+    if denominator == 0 {
+        0.0
+    } else {
+        2.0 * tp as f64 / denominator as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluation_report() {
+        let report = EvaluationReport::new(Split::Testing, vec![vec![5, 1], vec![2, 2]]);
+
+        assert_eq!(report.samples, 10);
+        assert!((report.accuracy - 0.7).abs() < 1e-12);
+        assert!((report.classes[0].precision - 5.0 / 7.0).abs() < 1e-12);
+        assert!((report.classes[1].recall - 0.5).abs() < 1e-12);
+        assert!((report.balanced_accuracy - (5.0 / 6.0 + 0.5) / 2.0).abs() < 1e-12);
+        // p_e = (6 * 7 + 4 * 3) / 100
+        assert!((report.cohen_kappa - (0.7 - 0.54) / 0.46).abs() < 1e-12);
+        // binary MCC = (TP TN - FP FN) / sqrt(...)
+        let mcc = (2.0 * 5.0 - 1.0 * 2.0) / (3.0f64 * 4.0 * 7.0 * 6.0).sqrt();
+        assert!((report.mcc - mcc).abs() < 1e-12);
+    }
+
+    #[test]
+    fn multi_label_report() {
+        let desired = vec![vec![true, false, true], vec![false, true, false]];
+        let predicted = vec![vec![true, false, false], vec![false, true, false]];
+        let report = MultiLabelReport::new(&predicted, &desired);
+
+        assert_eq!(report.confusion_matrices[2], [[1, 0], [1, 0]]);
+        assert!((report.hamming_loss - 1.0 / 6.0).abs() < 1e-12);
+        assert!((report.subset_accuracy - 0.5).abs() < 1e-12);
+        // tp = 2, fp = 0, fn = 1
+        assert!((report.micro_f1 - 0.8).abs() < 1e-12);
+        assert!((report.macro_f1 - 2.0 / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn regression_report() {
+        let desired = vec![vec![1.0], vec![2.0], vec![3.0], vec![4.0]];
+        let predicted = vec![vec![1.5], vec![2.0], vec![2.5], vec![4.0]];
+        let report = RegressionReport::new(&predicted, &desired);
+
+        assert!((report.mae - 0.25).abs() < 1e-12);
+        assert!((report.rmse - 0.125f64.sqrt()).abs() < 1e-12);
+        // SS_res = 0.5, SS_tot = 5
+        assert!((report.r2 - 0.9).abs() < 1e-12);
+        assert_eq!(report.residuals.mean, 0.0);
+        assert_eq!((report.residuals.min, report.residuals.max), (-0.5, 0.5));
+    }
+}
@@ -1,27 +1,125 @@
-use activation_functions::ActivationFunction;
-use layer::{Layer, LayerType};
-use rand::seq::SliceRandom;
+use activation_functions::{ActivationFunction, ActivationParameters};
+use calibration::{fit_platt, fit_temperature, Calibration, CalibrationMethod, CalibrationReport};
+use curves::CurveReport;
+use float::{Float, Precision};
+use layer::{output_errors, LayerGradients};
+use layer::{Layer, LayerType, Loss};
+use matrix::Matrix;
+use metrics::{EvaluationReport, Metric, MultiLabelReport, RegressionReport};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
 pub mod activation_functions;
+pub mod any;
+pub mod calibration;
+pub mod curves;
+pub mod explain;
+pub mod expression;
+pub mod float;
+pub mod inference;
 pub mod layer;
-pub mod neuron;
+pub mod matrix;
+pub mod metrics;
+pub mod model;
+pub mod stats;
 
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskType {
+    // exactly one class per row (argmax of the outputs)
+    #[default]
+    Classification,
+    // any number of classes per row, independent sigmoid outputs
+    MultiLabel,
+    // continuous targets
+    Regression,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Split {
+    Training,
+    Validation,
+    Testing,
+}
+
+/**
+ * Multilayer network computing in T (f64 unless trained in f32).
+ *
+ * losses, metrics and predictions are reported in f64 whatever the precision.
+ */
 #[derive(Clone, Debug)]
-pub struct NeuralNetwork {
-    pub layers: Vec<Layer>,
+pub struct NeuralNetwork<T: Float = f64> {
+    pub layers: Vec<Layer<T>>,
     pub mse: f64,
     pub mse_validation: f64,
-    pub training_data: Vec<(Vec<f64>, Vec<f64>)>,
+    pub training_data: Vec<(Vec<T>, Vec<T>)>,
     #[allow(dead_code)]
-    pub validation_data: Vec<(Vec<f64>, Vec<f64>)>,
+    pub validation_data: Vec<(Vec<T>, Vec<T>)>,
     #[allow(dead_code)]
-    pub testing_data: Vec<(Vec<f64>, Vec<f64>)>,
+    pub testing_data: Vec<(Vec<T>, Vec<T>)>,
     pub normalization_factors: Vec<(f64, f64)>,
+    // ε used to mix the training targets with the uniform distribution
+    pub label_smoothing: f64,
+    pub task_type: TaskType,
+    // per label decision thresholds used in multi-label mode
+    pub label_thresholds: Vec<f64>,
+    // fitted on the validation data, applied by predict
+    pub calibration: Calibration,
+    // learning rate
+    pub alpha: f64,
+    // samples per weight update, 1 is plain stochastic gradient descent
+    pub batch_size: usize,
+    // average L2 norm of the weight and threshold gradients of the updates of the last epoch
+    pub gradient_norm: f64,
+    // the same per layer
+    pub layer_gradient_norms: Vec<f64>,
+    // seed of the weight initialization and data shuffling, random when None
+    pub seed: Option<u64>,
 }
 
-impl NeuralNetwork {
+// samples per rayon task when computing the gradients of a batch.
+// fixed (instead of derived from the thread count) so the summation order,
+// and therefore the result, does not depend on the machine.
+const GRADIENT_CHUNK_SIZE: usize = 16;
+
+impl<T: Float> NeuralNetwork<T> {
     #[allow(dead_code)]
+    #[allow(clippy::too_many_arguments)]
     pub fn new<I: Clone + Copy + Into<f64>, O: Clone + Copy + Into<f64>>(
+        inputs_count: usize,
+        hidden_layers_topology: Vec<usize>,
+        hidden_layers_activation_functions: &[ActivationFunction],
+        outputs_count: usize,
+        output_layer_activation_function: ActivationFunction,
+        alpha: f64,
+        training_data: Vec<(Vec<I>, Vec<O>)>,
+        validation_data: Vec<(Vec<I>, Vec<O>)>,
+        testing_data: Vec<(Vec<I>, Vec<O>)>,
+    ) -> Self {
+        Self::with_seed(
+            None,
+            inputs_count,
+            hidden_layers_topology,
+            hidden_layers_activation_functions,
+            outputs_count,
+            output_layer_activation_function,
+            alpha,
+            training_data,
+            validation_data,
+            testing_data,
+        )
+    }
+
+    /**
+     * Same as new, but the initial weights and the data order are derived from the seed
+     * so two networks built with the same seed and data train identically.
+     */
+    #[allow(dead_code)]
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_seed<I: Clone + Copy + Into<f64>, O: Clone + Copy + Into<f64>>(
+        seed: Option<u64>,
         inputs_count: usize,
         hidden_layers_topology: Vec<usize>,
         hidden_layers_activation_functions: &[ActivationFunction],
@@ -32,7 +130,10 @@ impl NeuralNetwork {
         mut validation_data: Vec<(Vec<I>, Vec<O>)>,
         mut testing_data: Vec<(Vec<I>, Vec<O>)>,
     ) -> Self {
-        let mut rng = rand::thread_rng();
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
         training_data.shuffle(&mut rng);
         validation_data.shuffle(&mut rng);
         testing_data.shuffle(&mut rng);
@@ -53,7 +154,9 @@ impl NeuralNetwork {
                     w[0],
                     w[1],
                     activation_function.clone(),
+                    ActivationParameters::default(),
                     alpha,
+                    &mut rng,
                 )
             })
             .collect::<Vec<_>>();
@@ -64,7 +167,9 @@ impl NeuralNetwork {
             *topology.last().unwrap(),
             outputs_count,
             output_layer_activation_function,
+            ActivationParameters::default(),
             alpha,
+            &mut rng,
         ));
 
         // if let Some(output_layer) = layers.iter_mut().last() {
@@ -74,8 +179,14 @@ impl NeuralNetwork {
         let training_data = training_data
             .iter()
             .map(|(inputs, y_desired)| {
-                let inputs = inputs.iter().map(|&i| i.into()).collect::<Vec<f64>>();
-                let y_desired = y_desired.iter().map(|&i| i.into()).collect::<Vec<f64>>();
+                let inputs = inputs
+                    .iter()
+                    .map(|&i| T::from_f64(i.into()))
+                    .collect::<Vec<T>>();
+                let y_desired = y_desired
+                    .iter()
+                    .map(|&i| T::from_f64(i.into()))
+                    .collect::<Vec<T>>();
                 (inputs, y_desired)
             })
             .collect::<Vec<_>>();
@@ -83,8 +194,14 @@ impl NeuralNetwork {
         let validation_data = validation_data
             .iter()
             .map(|(inputs, y_desired)| {
-                let inputs = inputs.iter().map(|&i| i.into()).collect::<Vec<f64>>();
-                let y_desired = y_desired.iter().map(|&i| i.into()).collect::<Vec<f64>>();
+                let inputs = inputs
+                    .iter()
+                    .map(|&i| T::from_f64(i.into()))
+                    .collect::<Vec<T>>();
+                let y_desired = y_desired
+                    .iter()
+                    .map(|&i| T::from_f64(i.into()))
+                    .collect::<Vec<T>>();
                 (inputs, y_desired)
             })
             .collect::<Vec<_>>();
@@ -92,8 +209,14 @@ impl NeuralNetwork {
         let testing_data = testing_data
             .iter()
             .map(|(inputs, y_desired)| {
-                let inputs = inputs.iter().map(|&i| i.into()).collect::<Vec<f64>>();
-                let y_desired = y_desired.iter().map(|&i| i.into()).collect::<Vec<f64>>();
+                let inputs = inputs
+                    .iter()
+                    .map(|&i| T::from_f64(i.into()))
+                    .collect::<Vec<T>>();
+                let y_desired = y_desired
+                    .iter()
+                    .map(|&i| T::from_f64(i.into()))
+                    .collect::<Vec<T>>();
                 (inputs, y_desired)
             })
             .collect::<Vec<_>>();
@@ -106,6 +229,15 @@ impl NeuralNetwork {
             validation_data: validation_data.to_vec(), // validation_data.to_vec(),
             testing_data: testing_data.to_vec(),       // test_data.to_vec(),
             normalization_factors: vec![],
+            label_smoothing: 0.0,
+            task_type: TaskType::Classification,
+            label_thresholds: vec![],
+            calibration: Calibration::None,
+            alpha,
+            batch_size: 1,
+            gradient_norm: 0.0,
+            layer_gradient_norms: vec![],
+            seed,
         };
 
         nn.normalize(inputs_count);
@@ -121,82 +253,157 @@ impl NeuralNetwork {
             .for_each(|(inputs, _)| {
                 inputs.iter().enumerate().for_each(|(i, x)| {
                     if let Some(nf) = self.normalization_factors.get_mut(i) {
-                        if *x < nf.0 {
-                            nf.0 = *x;
+                        let x = x.as_f64();
+                        if x < nf.0 {
+                            nf.0 = x;
                         }
-                        if *x > nf.1 {
-                            nf.1 = *x;
+                        if x > nf.1 {
+                            nf.1 = x;
                         }
                     }
                 });
             });
 
-        // normalize training data
-        self.training_data.iter_mut().for_each(|(inputs, _)| {
-            inputs.iter_mut().enumerate().for_each(|(i, x)| {
-                if let Some(nf) = self.normalization_factors.get(i) {
-                    *x = (*x - nf.0) / (nf.1 - nf.0);
-                }
+        let normalization_factors = &self.normalization_factors;
+        // normalize training, validation and testing data
+        self.training_data
+            .iter_mut()
+            .chain(self.validation_data.iter_mut())
+            .chain(self.testing_data.iter_mut())
+            .for_each(|(inputs, _)| {
+                inputs.iter_mut().enumerate().for_each(|(i, x)| {
+                    if let Some(nf) = normalization_factors.get(i) {
+                        *x = T::from_f64((x.as_f64() - nf.0) / (nf.1 - nf.0));
+                    }
+                });
             });
-        });
+    }
 
-        // normalize validation data
-        self.validation_data.iter_mut().for_each(|(inputs, _)| {
-            inputs.iter_mut().enumerate().for_each(|(i, x)| {
-                if let Some(nf) = self.normalization_factors.get(i) {
-                    *x = (*x - nf.0) / (nf.1 - nf.0);
-                }
-            });
-        });
+    // the floating point type this network computes with
+    pub fn precision(&self) -> Precision {
+        T::PRECISION
+    }
 
-        // normalize testing data
-        self.testing_data.iter_mut().for_each(|(inputs, _)| {
-            inputs.iter_mut().enumerate().for_each(|(i, x)| {
-                if let Some(nf) = self.normalization_factors.get(i) {
-                    *x = (*x - nf.0) / (nf.1 - nf.0);
-                }
-            });
-        });
+    /**
+     * Sets the label smoothing factor used while training.
+     *
+     * Targets become (1 - ε) * y_desired + ε / K (ε / 2 for multi-label), clamped to 0 <= ε < 1.
+     * Regression targets are never smoothed.
+     */
+    #[allow(dead_code)]
+    pub fn set_label_smoothing(&mut self, label_smoothing: f64) {
+        self.label_smoothing = label_smoothing.clamp(0.0, 1.0 - f64::EPSILON);
     }
 
-    fn normalize_input(&self, inputs: &Vec<f64>) -> Vec<f64> {
-        inputs
-            .iter()
-            .enumerate()
-            .map(|(i, x)| {
-                if let Some(nf) = self.normalization_factors.get(i) {
-                    (*x - nf.0) / (nf.1 - nf.0)
-                } else {
-                    *x
+    /**
+     * Sets the activation parameters of each layer (hidden layers first, then the output layer).
+     */
+    #[allow(dead_code)]
+    pub fn set_activation_parameters(&mut self, parameters: &[ActivationParameters]) {
+        self.layers
+            .iter_mut()
+            .zip(parameters)
+            .for_each(|(layer, &p)| layer.set_activation_parameters(p));
+    }
+
+    /**
+     * Switches between single label classification, multi-label classification and regression.
+     *
+     * multi-label mode trains independent sigmoid outputs with binary cross entropy,
+     * regression replaces softmax outputs with linear ones.
+     */
+    #[allow(dead_code)]
+    pub fn set_task_type(&mut self, task_type: TaskType) {
+        self.task_type = task_type;
+        if let Some(output_layer) = self.layers.last_mut() {
+            match task_type {
+                TaskType::MultiLabel => {
+                    output_layer.set_activation_function(ActivationFunction::Sigmoid);
+                    output_layer.loss = Loss::BinaryCrossEntropy;
                 }
-            })
-            .collect()
+                TaskType::Classification => output_layer.loss = Loss::MeanSquaredError,
+                TaskType::Regression => {
+                    // softmax outputs always sum to one and cannot fit continuous targets
+                    if let ActivationFunction::Softmax = output_layer.activation_function {
+                        output_layer.set_activation_function(ActivationFunction::Linear);
+                    }
+                    output_layer.loss = Loss::MeanSquaredError;
+                }
+            }
+        }
     }
 
+    /**
+     * Decision thresholds for each label in multi-label mode (missing ones default to 0.5).
+     */
     #[allow(dead_code)]
-    pub fn forward(&mut self, index: usize) -> Vec<f64> {
-        // convert inputs to f64
-        let (inputs, _) = self.training_data.get(index).unwrap();
-        // let inputs = inputs.iter().map(|&i| i.into()).collect::<Vec<f64>>();
+    pub fn set_label_thresholds(&mut self, label_thresholds: Vec<f64>) {
+        self.label_thresholds = label_thresholds;
+    }
 
-        // iterate over all layers and feed each layer with the previous layer outputs.
-        self.layers
-            .iter_mut()
-            .fold(inputs.clone(), |layer_inputs, layer| {
-                layer.forward(&layer_inputs)
-            })
+    fn label_threshold(&self, index: usize) -> f64 {
+        self.label_thresholds.get(index).copied().unwrap_or(0.5)
+    }
+
+    /**
+     * Number of training samples whose gradients are averaged into one weight update.
+     */
+    #[allow(dead_code)]
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    #[allow(dead_code)]
+    pub fn forward(&mut self, index: usize) -> Vec<T> {
+        self.forward_batch(&[index]).row(0).to_vec()
+    }
+
+    /**
+     * feeds the given training samples through all layers, one row per sample.
+     */
+    pub fn forward_batch(&mut self, indices: &[usize]) -> &Matrix<T> {
+        let inputs = Matrix::from_rows(
+            &indices
+                .iter()
+                .map(|&i| &self.training_data[i].0)
+                .collect::<Vec<_>>(),
+        );
+
+        // feed each layer with the previous layer outputs.
+        for i in 0..self.layers.len() {
+            let (previous_layers, layers) = self.layers.split_at_mut(i);
+            let layer_inputs = previous_layers
+                .last()
+                .map_or(&inputs, |l| &l.current_outputs);
+            layers[0].forward(layer_inputs);
+        }
+        &self.layers.last().unwrap().current_outputs
     }
 
     #[allow(dead_code)]
     pub fn backward(&mut self, index: usize) {
-        let (_, y_desired) = self.training_data.get(index).unwrap();
-        // let y_desired = y_desired.iter().map(|&i| i.into()).collect::<Vec<f64>>();
+        self.backward_batch(&[index]);
+    }
+
+    pub fn backward_batch(&mut self, indices: &[usize]) {
+        let y_desired = Matrix::from_rows(
+            &indices
+                .iter()
+                .map(|&i| {
+                    smooth_labels(
+                        &self.training_data[i].1,
+                        self.label_smoothing,
+                        self.task_type,
+                    )
+                })
+                .collect::<Vec<_>>(),
+        );
 
         self.layers
             .iter_mut()
             .rev()
-            .fold(None, |nl: Option<&Layer>, l| {
-                l.backward(y_desired, nl);
+            .fold(None, |nl: Option<&Layer<T>>, l| {
+                l.backward(&y_desired, nl);
                 Some(l)
             });
     }
@@ -208,18 +415,32 @@ impl NeuralNetwork {
 
     #[allow(dead_code)]
     pub fn epoch(&mut self) -> (f64, f64) {
+        // weights are about to change, a previous calibration no longer applies
+        self.calibration = Calibration::None;
         self.mse = 0.0;
         self.mse_validation = 0.0;
 
         let n = self.training_data.len() as f64;
-        // let training_data = self.training_data.clone();
-        let len = self.training_data.len();
-        (0..len).for_each(|i| {
-            self.mse += self.iteration(i);
+        let indices = (0..self.training_data.len()).collect::<Vec<_>>();
+        let batches = indices.chunks(self.batch_size.max(1));
+        let updates = batches.len().max(1) as f64;
+        let mut gradient_norm = 0.0;
+        let mut layer_gradient_norms = vec![0.0; self.layers.len()];
+        batches.for_each(|batch| {
+            self.mse += self.iteration_batch(batch);
+            let norms = self
+                .layers
+                .iter()
+                .map(|l| l.gradient_norm_squared())
+                .collect::<Vec<_>>();
+            gradient_norm += norms.iter().sum::<f64>().sqrt();
+            layer_gradient_norms
+                .iter_mut()
+                .zip(norms)
+                .for_each(|(n, norm)| *n += norm.sqrt() / updates);
         });
-        // self.training_data.iter().for_each(|(inputs, y_desired)| {
-        //     self.mse += self.next_iter(inputs, y_desired);
-        // });
+        self.gradient_norm = gradient_norm / updates;
+        self.layer_gradient_norms = layer_gradient_norms;
         self.mse /= n;
 
         self.mse_validation = self.calculate_mse(&self.validation_data);
@@ -229,39 +450,250 @@ impl NeuralNetwork {
 
     #[allow(dead_code)]
     pub fn iteration(&mut self, index: usize) -> f64 {
-        self.forward(index);
-        self.backward(index);
-        self.commit();
-
-        let (_, y_desired) = self.training_data.get(index).unwrap();
+        self.iteration_batch(&[index])
+    }
 
-        // return mse for this iteration
+    /**
+     * one weight update from the given training samples.
+     *
+     * the gradients are computed in parallel on slices of the batch, averaged,
+     * then applied once. returns the summed squared error of those samples (before the update).
+     */
+    pub fn iteration_batch(&mut self, indices: &[usize]) -> f64 {
+        let (mut gradients, squared_error) = self.batch_gradients(indices);
+        let scale = T::one() / T::from_f64(indices.len().max(1) as f64);
         self.layers
+            .iter_mut()
+            .zip(gradients.iter_mut())
+            .for_each(|(layer, gradients)| {
+                gradients.scale(scale);
+                layer.apply(gradients);
+                layer.gradients = std::mem::take(gradients);
+            });
+        squared_error
+    }
+
+    /**
+     * summed gradients of every layer and summed squared error of the given samples.
+     *
+     * workers share the network read-only, each one handles GRADIENT_CHUNK_SIZE samples.
+     * the partial sums are reduced in chunk order so a seeded run is reproducible.
+     */
+    pub fn batch_gradients(&self, indices: &[usize]) -> (Vec<LayerGradients<T>>, f64) {
+        if indices.len() <= GRADIENT_CHUNK_SIZE {
+            return self.gradients(indices);
+        }
+        let partials = indices
+            .par_chunks(GRADIENT_CHUNK_SIZE)
+            .map(|chunk| self.gradients(chunk))
+            .collect::<Vec<_>>();
+        let mut partials = partials.into_iter();
+        let (mut gradients, mut squared_error) = partials.next().unwrap();
+        partials.for_each(|(g, e)| {
+            gradients.iter_mut().zip(&g).for_each(|(a, b)| a.add(b));
+            squared_error += e;
+        });
+        (gradients, squared_error)
+    }
+
+    /**
+     * forward and backward pass of the given training samples without updating the network.
+     */
+    fn gradients(&self, indices: &[usize]) -> (Vec<LayerGradients<T>>, f64) {
+        let inputs = Matrix::from_rows(
+            &indices
+                .iter()
+                .map(|&i| &self.training_data[i].0)
+                .collect::<Vec<_>>(),
+        );
+        let y_desired = Matrix::from_rows(
+            &indices
+                .iter()
+                .map(|&i| {
+                    smooth_labels(
+                        &self.training_data[i].1,
+                        self.label_smoothing,
+                        self.task_type,
+                    )
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        // (inputs, weighted sums) of every layer
+        let mut passes = Vec::with_capacity(self.layers.len());
+        let outputs = self.layers.iter().fold(inputs, |layer_inputs, layer| {
+            let x = layer.weighted_sums(&layer_inputs);
+            let outputs = layer.activate(&x);
+            passes.push((layer_inputs, x));
+            outputs
+        });
+
+        let squared_error = indices
             .iter()
-            .last()
-            .unwrap()
-            .neurons
+            .enumerate()
+            .map(|(b, &i)| {
+                outputs
+                    .row(b)
+                    .iter()
+                    .zip(&self.training_data[i].1)
+                    .fold(0.0, |acc, (&y, &y_desired)| {
+                        acc + (y_desired - y).as_f64().powi(2)
+                    })
+            })
+            .sum::<f64>();
+
+        let mut errors = output_errors(&y_desired, &outputs);
+        let mut gradients = self
+            .layers
             .iter()
-            .zip(y_desired)
-            .fold(0.0, |acc, (n, &y_desired)| acc + (y_desired - n.y).powi(2))
+            .zip(passes)
+            .rev()
+            .map(|(layer, (layer_inputs, x))| {
+                let (gradiant_errors, parameters) = layer.error_signals(&x, &errors);
+                errors = gradiant_errors.mul(&layer.weights);
+                layer.gradients(&layer_inputs, &gradiant_errors, parameters)
+            })
+            .collect::<Vec<_>>();
+        gradients.reverse();
+        (gradients, squared_error)
     }
 
     #[allow(dead_code)]
     pub fn predict<I: Clone + Copy + Into<f64>>(&self, inputs: &Vec<I>) -> Vec<f64> {
         let inputs = inputs.iter().map(|&i| i.into()).collect::<Vec<f64>>();
-        let inputs = self.normalize_input(&inputs);
-        self.predict_normalized(&inputs)
+        let mut outputs = vec![0.0; self.outputs_count()];
+        self.predict_into(&inputs, &mut self.scratch(), &mut outputs);
+        outputs
     }
 
-    fn predict_normalized(&self, inputs: &Vec<f64>) -> Vec<f64> {
-        self.layers
+    fn predict_normalized(&self, inputs: &[T]) -> Vec<f64> {
+        if let Calibration::None = self.calibration {
+            return self.predict_uncalibrated(inputs);
+        }
+        self.calibration
+            .apply(&self.predict_logits(inputs), self.exclusive_outputs())
+    }
+
+    fn predict_uncalibrated(&self, inputs: &[T]) -> Vec<f64> {
+        to_f64(
+            &self
+                .layers
+                .iter()
+                .fold(inputs.to_vec(), |layer_inputs, layer| {
+                    layer.predict(&layer_inputs)
+                }),
+        )
+    }
+
+    // output layer weighted sums (before softmax / sigmoid)
+    fn predict_logits(&self, inputs: &[T]) -> Vec<f64> {
+        let (output_layer, hidden_layers) = self.layers.split_last().unwrap();
+        let hidden_outputs = hidden_layers
             .iter()
-            .fold(inputs.clone(), |layer_inputs, layer| {
+            .fold(inputs.to_vec(), |layer_inputs, layer| {
                 layer.predict(&layer_inputs)
+            });
+        to_f64(&output_layer.predict_logits(&hidden_outputs))
+    }
+
+    /**
+     * Fits temperature or Platt scaling on the validation data and stores it in the network.
+     *
+     * the calibration is dropped as soon as training continues.
+     */
+    #[allow(dead_code)]
+    pub fn calibrate(&mut self, method: CalibrationMethod) -> Result<(), String> {
+        if self.task_type == TaskType::Regression {
+            return Err("Calibration needs a classification task".to_string());
+        }
+        if method != CalibrationMethod::None && self.validation_data.is_empty() {
+            return Err("Calibration needs validation data".to_string());
+        }
+
+        let exclusive = self.exclusive_outputs();
+        let logits = self
+            .validation_data
+            .iter()
+            .map(|(inputs, _)| self.predict_logits(inputs))
+            .collect::<Vec<_>>();
+
+        self.calibration = match method {
+            CalibrationMethod::None => Calibration::None,
+            CalibrationMethod::Temperature => {
+                let targets = self
+                    .validation_data
+                    .iter()
+                    .map(|(_, y_desired)| to_f64(y_desired))
+                    .collect::<Vec<_>>();
+                Calibration::Temperature(fit_temperature(&logits, &targets, exclusive))
+            }
+            CalibrationMethod::Platt => {
+                let positives = self
+                    .validation_data
+                    .iter()
+                    .map(|(_, y_desired)| self.positives(y_desired))
+                    .collect::<Vec<_>>();
+                Calibration::Platt(
+                    (0..self.layers.last().unwrap().neurons_count())
+                        .map(|j| {
+                            let z = logits.iter().map(|z| z[j]).collect::<Vec<_>>();
+                            let p = positives.iter().map(|p| p[j]).collect::<Vec<_>>();
+                            fit_platt(&z, &p)
+                        })
+                        .collect(),
+                )
+            }
+        };
+        Ok(())
+    }
+
+    // softmax outputs sum to one, so must their calibrated probabilities
+    fn exclusive_outputs(&self) -> bool {
+        matches!(
+            self.layers.last().unwrap().activation_function,
+            ActivationFunction::Softmax
+        )
+    }
+
+    /**
+     * Expected calibration error and reliability diagram bins.
+     *
+     * uses the top label confidence for single label classification and every output otherwise.
+     */
+    #[allow(dead_code)]
+    pub fn calibration_report(&self, split: Split, bins_count: usize) -> CalibrationReport {
+        let samples = self
+            .data(split)
+            .iter()
+            .flat_map(|(inputs, y_desired)| {
+                let outputs = self.predict_normalized(inputs);
+                match self.task_type {
+                    TaskType::Classification => {
+                        let predicted = argmax(&outputs);
+                        vec![(outputs[predicted], predicted == argmax(y_desired))]
+                    }
+                    _ => outputs
+                        .into_iter()
+                        .zip(self.positives(y_desired))
+                        .collect::<Vec<_>>(),
+                }
             })
+            .collect::<Vec<_>>();
+        CalibrationReport::new(split, self.calibration.clone(), &samples, bins_count)
     }
 
-    fn calculate_mse(&self, data: &Vec<(Vec<f64>, Vec<f64>)>) -> f64 {
+    // which classes a row of desired outputs belongs to
+    fn positives(&self, y_desired: &[T]) -> Vec<bool> {
+        match self.task_type {
+            TaskType::MultiLabel => y_desired.iter().map(|t| t.as_f64() >= 0.5).collect(),
+            _ => {
+                let class = argmax(y_desired);
+                (0..y_desired.len()).map(|j| j == class).collect()
+            }
+        }
+    }
+
+    fn calculate_mse(&self, data: &[(Vec<T>, Vec<T>)]) -> f64 {
         let mut result = 0.0;
         let n = data.len() as f64;
 
@@ -272,38 +704,43 @@ impl NeuralNetwork {
         result
     }
 
-    fn calculate_mse_for_one_row(&self, inputs: &Vec<f64>, y_desired: &Vec<f64>) -> f64 {
+    fn calculate_mse_for_one_row(&self, inputs: &[T], y_desired: &[T]) -> f64 {
         let outputs = self.predict_normalized(inputs);
         outputs
             .iter()
             .zip(y_desired)
             .fold(0.0, |acc, (output, &desired)| {
-                acc + (desired - output).powi(2)
+                acc + (desired.as_f64() - output).powi(2)
             })
     }
 
+    pub fn data(&self, split: Split) -> &Vec<(Vec<T>, Vec<T>)> {
+        match split {
+            Split::Training => &self.training_data,
+            Split::Validation => &self.validation_data,
+            Split::Testing => &self.testing_data,
+        }
+    }
+
+    // confusion matrix of the testing data
     #[allow(dead_code)]
     pub fn confusion_matrix(&self) -> Vec<Vec<usize>> {
-        let output_len = self.layers.iter().last().unwrap().neurons.len();
-        self.testing_data
+        self.split_confusion_matrix(Split::Testing)
+    }
+
+    /**
+     * rows are the desired classes, columns the predicted ones.
+     */
+    pub fn split_confusion_matrix(&self, split: Split) -> Vec<Vec<usize>> {
+        let output_len = self.layers.iter().last().unwrap().neurons_count();
+        self.data(split)
             .iter()
             .map(|(inputs, y_desired)| {
                 let outputs = self.predict_normalized(inputs);
-                let max_index = outputs
-                    .iter()
-                    .enumerate()
-                    .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-                    .unwrap()
-                    .0;
-                let y_desired_index = y_desired
-                    .clone()
-                    .into_iter()
-                    .map(|i| i.into())
-                    .enumerate()
-                    .max_by(|a: &(usize, f64), b: &(usize, f64)| a.1.partial_cmp(&b.1).unwrap())
-                    .unwrap()
-                    .0;
-                return (max_index, y_desired_index);
+                // soft targets are assigned to their most probable class
+                let max_index = argmax(&outputs);
+                let y_desired_index = argmax(y_desired);
+                (max_index, y_desired_index)
             })
             .fold(
                 vec![vec![0; output_len]; output_len],
@@ -315,22 +752,177 @@ impl NeuralNetwork {
         // .collect::<Vec<(usize, usize)>>()
     }
 
+    #[allow(dead_code)]
+    pub fn evaluation_report(&self, split: Split) -> EvaluationReport {
+        EvaluationReport::new(split, self.split_confusion_matrix(split))
+    }
+
+    /**
+     * Evaluation reports for the training, validation and testing data (empty splits are skipped).
+     */
+    #[allow(dead_code)]
+    pub fn evaluation_reports(&self) -> Vec<EvaluationReport> {
+        [Split::Training, Split::Validation, Split::Testing]
+            .into_iter()
+            .filter(|&split| !self.data(split).is_empty())
+            .map(|split| self.evaluation_report(split))
+            .collect()
+    }
+
+    /**
+     * Average cross entropy -SUM(y_desired * ln(y_actual)) over the training data.
+     *
+     * Works with one-hot as well as probabilistic target vectors.
+     */
     #[allow(dead_code)]
     pub fn cross_entropy_loss(&self) -> f64 {
+        self.split_cross_entropy_loss(Split::Training)
+    }
+
+    pub fn split_cross_entropy_loss(&self, split: Split) -> f64 {
+        if self.task_type == TaskType::MultiLabel {
+            return self.binary_cross_entropy_loss(split);
+        }
+
+        let data = self.data(split);
         let mut total_loss = 0.0;
 
-        self.training_data.iter().for_each(|(inputs, y_desired)| {
+        data.iter().for_each(|(inputs, y_desired)| {
             let mut sample_loss = 0.0;
             let y_actual = self.predict_normalized(inputs);
-            for (j, &target_value) in y_desired.iter().enumerate() {
-                if target_value == 1.0 {
-                    sample_loss -= y_actual[j].ln(); // Only take log of the predicted probability where target is 1
+            for (&target_value, &y) in y_desired.iter().zip(&y_actual) {
+                let target_value = target_value.as_f64();
+                if target_value > 0.0 {
+                    // clamp to avoid ln(0) when the network is fully confident
+                    sample_loss -= target_value * y.max(f64::EPSILON).ln();
                 }
             }
             total_loss += sample_loss;
         });
 
-        total_loss / self.training_data.len() as f64
+        total_loss / data.len() as f64
+    }
+
+    /**
+     * Current value of a per-epoch metric, None when it does not apply to the task type.
+     */
+    #[allow(dead_code)]
+    pub fn metric(&self, metric: Metric) -> Option<f64> {
+        let split = match metric {
+            Metric::LearningRate => return Some(self.alpha),
+            Metric::GradientNorm => return Some(self.gradient_norm),
+            Metric::TrainingAccuracy | Metric::TrainingCrossEntropy | Metric::TrainingF1 => {
+                Split::Training
+            }
+            Metric::ValidationAccuracy | Metric::ValidationCrossEntropy | Metric::ValidationF1 => {
+                Split::Validation
+            }
+        };
+        if self.task_type == TaskType::Regression || self.data(split).is_empty() {
+            return None;
+        }
+        match metric {
+            Metric::TrainingCrossEntropy | Metric::ValidationCrossEntropy => {
+                Some(self.split_cross_entropy_loss(split))
+            }
+            Metric::TrainingAccuracy | Metric::ValidationAccuracy => Some(match self.task_type {
+                TaskType::MultiLabel => self.split_multi_label_report(split).subset_accuracy,
+                _ => self.evaluation_report(split).accuracy,
+            }),
+            _ => Some(match self.task_type {
+                TaskType::MultiLabel => self.split_multi_label_report(split).macro_f1,
+                _ => self.evaluation_report(split).macro_average.f1,
+            }),
+        }
+    }
+
+    /**
+     * One-vs-rest ROC and precision-recall curves computed from the predicted probabilities.
+     */
+    #[allow(dead_code)]
+    pub fn curve_report(&self, split: Split) -> CurveReport {
+        let (scores, positives): (Vec<_>, Vec<_>) = self
+            .data(split)
+            .iter()
+            .map(|(inputs, y_desired)| (self.predict_normalized(inputs), self.positives(y_desired)))
+            .unzip();
+        CurveReport::new(split, &scores, &positives)
+    }
+
+    // curves of the validation and testing data
+    #[allow(dead_code)]
+    pub fn curve_reports(&self) -> Vec<CurveReport> {
+        [Split::Validation, Split::Testing]
+            .into_iter()
+            .filter(|&split| !self.data(split).is_empty())
+            .map(|split| self.curve_report(split))
+            .collect()
+    }
+
+    /**
+     * Average SUM(-y_desired * ln(y) - (1 - y_desired) * ln(1 - y)) over the data of a split.
+     */
+    fn binary_cross_entropy_loss(&self, split: Split) -> f64 {
+        let data = self.data(split);
+        let total_loss = data
+            .iter()
+            .map(|(inputs, y_desired)| {
+                let y_actual = self.predict_normalized(inputs);
+                y_desired
+                    .iter()
+                    .zip(&y_actual)
+                    .map(|(&t, &y)| {
+                        let t = t.as_f64();
+                        let y = y.clamp(f64::EPSILON, 1.0 - f64::EPSILON);
+                        -t * y.ln() - (1.0 - t) * (1.0 - y).ln()
+                    })
+                    .sum::<f64>()
+            })
+            .sum::<f64>();
+
+        total_loss / data.len() as f64
+    }
+
+    /**
+     * Per label confusion matrices, hamming loss, subset accuracy and F1 on the testing data.
+     */
+    #[allow(dead_code)]
+    pub fn multi_label_report(&self) -> MultiLabelReport {
+        self.split_multi_label_report(Split::Testing)
+    }
+
+    pub fn split_multi_label_report(&self, split: Split) -> MultiLabelReport {
+        let (predicted, desired): (Vec<_>, Vec<_>) = self
+            .data(split)
+            .iter()
+            .map(|(inputs, y_desired)| {
+                let outputs = self.predict_normalized(inputs);
+                let predicted = outputs
+                    .iter()
+                    .enumerate()
+                    .map(|(j, &y)| y >= self.label_threshold(j))
+                    .collect::<Vec<_>>();
+                let desired = y_desired
+                    .iter()
+                    .map(|t| t.as_f64() >= 0.5)
+                    .collect::<Vec<_>>();
+                (predicted, desired)
+            })
+            .unzip();
+        MultiLabelReport::new(&predicted, &desired)
+    }
+
+    /**
+     * MAE, RMSE, R² and residual statistics on the testing data.
+     */
+    #[allow(dead_code)]
+    pub fn regression_report(&self) -> RegressionReport {
+        let (predicted, desired): (Vec<_>, Vec<_>) = self
+            .testing_data
+            .iter()
+            .map(|(inputs, y_desired)| (self.predict_normalized(inputs), to_f64(y_desired)))
+            .unzip();
+        RegressionReport::new(&predicted, &desired)
     }
 
     #[allow(dead_code)]
@@ -339,6 +931,47 @@ impl NeuralNetwork {
     }
 }
 
+/**
+ * Mixes the targets with the uniform distribution over all classes,
+ * or over {0, 1} for multi-label targets where every output is its own binary label.
+ *
+ * Regression targets are not labels and are left as they are.
+ */
+fn smooth_labels<T: Float>(y_desired: &[T], label_smoothing: f64, task_type: TaskType) -> Vec<T> {
+    if label_smoothing == 0.0 || task_type == TaskType::Regression {
+        return y_desired.to_vec();
+    }
+    let k = match task_type {
+        TaskType::MultiLabel => 2.0,
+        _ => y_desired.len() as f64,
+    };
+    y_desired
+        .iter()
+        .map(|&y| T::from_f64((1.0 - label_smoothing) * y.as_f64() + label_smoothing / k))
+        .collect()
+}
+
+fn to_f64<T: Float>(values: &[T]) -> Vec<f64> {
+    values.iter().map(|v| v.as_f64()).collect()
+}
+
+/**
+ * Index of the largest value (first one wins on ties).
+ */
+fn argmax<T: Float>(values: &[T]) -> usize {
+    values
+        .iter()
+        .enumerate()
+        .fold((0, T::min_value()), |(best_index, best), (i, &v)| {
+            if v > best {
+                (i, v)
+            } else {
+                (best_index, best)
+            }
+        })
+        .0
+}
+
 // struct NeuronParameters {
 //     weights: Vec<f64>,
 //     threshold: f64,
@@ -348,3 +981,112 @@ impl NeuralNetwork {
 //     neurons: Vec<NeuronParameters>,
 //     layer_type: LayerType,
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network(seed: u64) -> NeuralNetwork {
+        let data = (0..200)
+            .map(|i| {
+                let class = i % 2;
+                let x = i as f64 / 200.;
+                (
+                    vec![x, (x * 7.).sin() + class as f64],
+                    vec![1. - class as f64, class as f64],
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut nn = NeuralNetwork::with_seed(
+            Some(seed),
+            2,
+            vec![8, 4],
+            &[ActivationFunction::Tanh, ActivationFunction::Relu],
+            2,
+            ActivationFunction::Softmax,
+            0.1,
+            data.clone(),
+            data,
+            vec![],
+        );
+        nn.set_batch_size(64);
+        nn
+    }
+
+    #[test]
+    fn label_smoothing_follows_the_task_type() {
+        let y = [0.0, 1.0, 0.0, 1.0];
+        let assert_close = |smoothed: Vec<f64>, expected: [f64; 4]| {
+            assert!(smoothed
+                .iter()
+                .zip(expected)
+                .all(|(a, b)| (a - b).abs() < 1e-12));
+        };
+        assert_close(
+            smooth_labels(&y, 0.2, TaskType::Classification),
+            [0.05, 0.85, 0.05, 0.85],
+        );
+        assert_close(
+            smooth_labels(&y, 0.2, TaskType::MultiLabel),
+            [0.1, 0.9, 0.1, 0.9],
+        );
+        assert_close(
+            smooth_labels(&y, 0.2, TaskType::Regression),
+            [0.0, 1.0, 0.0, 1.0],
+        );
+    }
+
+    #[test]
+    fn temperature_keeps_sigmoid_outputs_independent() {
+        let mut nn = network(5);
+        nn.layers
+            .last_mut()
+            .unwrap()
+            .set_activation_function(ActivationFunction::Sigmoid);
+        nn.epoch();
+        nn.calibrate(CalibrationMethod::Temperature).unwrap();
+        let Calibration::Temperature(t) = nn.calibration else {
+            panic!("temperature scaling expected");
+        };
+        let (inputs, _) = &nn.validation_data[0];
+        let expected = nn
+            .predict_logits(inputs)
+            .iter()
+            .map(|z| 1.0 / (1.0 + (-z / t).exp()))
+            .collect::<Vec<_>>();
+        nn.predict_normalized(inputs)
+            .iter()
+            .zip(expected)
+            .for_each(|(p, e)| assert!((p - e).abs() < 1e-9));
+    }
+
+    #[test]
+    fn seeded_parallel_training_is_deterministic() {
+        let (mut a, mut b) = (network(7), network(7));
+        (0..3).for_each(|_| {
+            a.epoch();
+            b.epoch();
+        });
+        assert_eq!(a.mse, b.mse);
+        assert_eq!(
+            format!("{:?}", a.get_parameters()),
+            format!("{:?}", b.get_parameters())
+        );
+    }
+
+    #[test]
+    fn parallel_gradients_match_a_single_pass() {
+        let nn = network(3);
+        let indices = (0..100).collect::<Vec<_>>();
+        let (parallel, parallel_error) = nn.batch_gradients(&indices);
+        let (sequential, sequential_error) = nn.gradients(&indices);
+        assert!((parallel_error - sequential_error).abs() < 1e-9);
+        parallel.iter().zip(&sequential).for_each(|(p, s)| {
+            p.weights
+                .data()
+                .iter()
+                .zip(s.weights.data())
+                .for_each(|(a, b)| assert!((a - b).abs() < 1e-9));
+        });
+    }
+}
@@ -0,0 +1,198 @@
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    activation_functions::{
+        validate_activation_function, ActivationFunction, ActivationParameters,
+    },
+    calibration::Calibration,
+    float::{Float, Precision},
+    layer::{Layer, LayerType, Loss},
+    matrix::Matrix,
+    NeuralNetwork, TaskType,
+};
+
+/**
+ * Trained network as written to disk: everything predict needs, without the data.
+ *
+ * values are stored as f64, precision records the type the network was trained with.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub precision: Precision,
+    pub task_type: TaskType,
+    // (min, max) of every input
+    pub normalization_factors: Vec<(f64, f64)>,
+    #[serde(default)]
+    pub label_thresholds: Vec<f64>,
+    #[serde(default)]
+    pub calibration: Calibration,
+    pub alpha: f64,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    pub layers: Vec<ModelLayer>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelLayer {
+    pub layer_type: LayerType,
+    pub activation_function: ActivationFunction,
+    #[serde(default)]
+    pub activation_parameters: ActivationParameters,
+    pub loss: Loss,
+    // one row of input weights per neuron
+    pub weights: Vec<Vec<f64>>,
+    pub thresholds: Vec<f64>,
+    // learned activation parameter of each neuron (PRELU)
+    #[serde(default)]
+    pub learned_parameters: Vec<Option<f64>>,
+}
+
+impl<T: Float> NeuralNetwork<T> {
+    #[allow(dead_code)]
+    pub fn to_model(&self) -> Model {
+        Model {
+            precision: self.precision(),
+            task_type: self.task_type,
+            normalization_factors: self.normalization_factors.clone(),
+            label_thresholds: self.label_thresholds.clone(),
+            calibration: self.calibration.clone(),
+            alpha: self.alpha,
+            seed: self.seed,
+            layers: self
+                .layers
+                .iter()
+                .map(|layer| {
+                    let (weights, thresholds) = layer
+                        .get_parameters()
+                        .into_iter()
+                        .map(|(weights, threshold, _)| (weights, threshold))
+                        .unzip();
+                    ModelLayer {
+                        layer_type: layer.layer_type,
+                        activation_function: layer.activation_function.clone(),
+                        activation_parameters: layer.activation_parameters,
+                        loss: layer.loss,
+                        weights,
+                        thresholds,
+                        learned_parameters: layer.get_activation_parameters(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /**
+     * Rebuilds a network from a saved model, converting it to T if it was trained
+     * with another precision.
+     *
+     * the network has no training, validation or testing data.
+     */
+    #[allow(dead_code)]
+    pub fn from_model(model: &Model) -> Result<Self, String> {
+        let inputs_count = model
+            .layers
+            .first()
+            .and_then(|l| l.weights.first())
+            .map(|w| w.len())
+            .ok_or("The model has no layers")?;
+        if model.normalization_factors.len() != inputs_count {
+            return Err("The normalization factors do not match the inputs".to_string());
+        }
+
+        // weights are overwritten, the generator only satisfies Layer::new
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut previous_neurons_count = inputs_count;
+        let layers = model
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(index, l)| {
+                validate_activation_function(&l.activation_function)?;
+                if l.weights.len() != l.thresholds.len()
+                    || l.weights.iter().any(|w| w.len() != previous_neurons_count)
+                {
+                    return Err(format!("Layer {} has inconsistent weights", index + 1));
+                }
+                let mut layer = Layer::new(
+                    index + 1,
+                    l.layer_type,
+                    previous_neurons_count,
+                    l.thresholds.len(),
+                    l.activation_function.clone(),
+                    l.activation_parameters,
+                    model.alpha,
+                    &mut rng,
+                );
+                layer.weights = Matrix::from_rows(
+                    &l.weights
+                        .iter()
+                        .map(|w| w.iter().map(|&v| T::from_f64(v)).collect::<Vec<_>>())
+                        .collect::<Vec<_>>(),
+                );
+                layer.thresholds = l.thresholds.iter().map(|&v| T::from_f64(v)).collect();
+                layer.loss = l.loss;
+                layer.set_learned_parameters(&l.learned_parameters);
+                previous_neurons_count = l.thresholds.len();
+                Ok(layer)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(NeuralNetwork {
+            layers,
+            mse: 0.0,
+            mse_validation: 0.0,
+            training_data: vec![],
+            validation_data: vec![],
+            testing_data: vec![],
+            normalization_factors: model.normalization_factors.clone(),
+            label_smoothing: 0.0,
+            task_type: model.task_type,
+            label_thresholds: model.label_thresholds.clone(),
+            calibration: model.calibration.clone(),
+            alpha: model.alpha,
+            batch_size: 1,
+            gradient_norm: 0.0,
+            layer_gradient_norms: vec![],
+            seed: model.seed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f32_model_round_trip() {
+        let data = (0..40)
+            .map(|i| (vec![i as f64, (i * 7 % 11) as f64], vec![(i % 2) as f64]))
+            .collect::<Vec<_>>();
+        let mut nn = NeuralNetwork::<f32>::with_seed(
+            Some(1),
+            2,
+            vec![4],
+            &[ActivationFunction::Prelu],
+            1,
+            ActivationFunction::Sigmoid,
+            0.1,
+            data.clone(),
+            vec![],
+            vec![],
+        );
+        nn.epoch();
+
+        let json = serde_json::to_string(&nn.to_model()).unwrap();
+        let model: Model = serde_json::from_str(&json).unwrap();
+        assert_eq!(model.precision, Precision::F32);
+
+        // f32 values are exact in f64, both networks predict the same
+        let loaded = NeuralNetwork::<f64>::from_model(&model).unwrap();
+        data.iter().for_each(|(inputs, _)| {
+            let (a, b) = (nn.predict(inputs), loaded.predict(inputs));
+            assert!((a[0] - b[0]).abs() < 1e-5);
+        });
+    }
+}
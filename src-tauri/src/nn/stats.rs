@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    activation_functions::ActivationFunction, float::Float, matrix::Matrix, NeuralNetwork,
+};
+
+// training samples forwarded to measure dead and saturated units
+const STATS_SAMPLES: usize = 256;
+
+/**
+ * Limits past which the layer statistics report a warning.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StatsThresholds {
+    // gradient norm of a layer below which its gradients vanished
+    pub vanishing_gradient: f64,
+    // gradient norm of a layer above which its gradients exploded
+    pub exploding_gradient: f64,
+    // fraction of ReLU units that never activate
+    pub dead_units: f64,
+    // fraction of sigmoid / tanh outputs stuck in the flat ends
+    pub saturation: f64,
+    // bins of the weight histograms
+    pub histogram_bins: usize,
+}
+
+impl Default for StatsThresholds {
+    fn default() -> Self {
+        StatsThresholds {
+            vanishing_gradient: 1e-6,
+            exploding_gradient: 1e3,
+            dead_units: 0.5,
+            saturation: 0.5,
+            histogram_bins: 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkStats {
+    pub layers: Vec<LayerStats>,
+    pub warnings: Vec<StatsWarning>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayerStats {
+    pub weight_mean: f64,
+    pub weight_std: f64,
+    pub weight_histogram: Histogram,
+    // average over the updates of the last epoch
+    pub gradient_norm: f64,
+    // fraction of ReLU neurons with no positive weighted sum on any sample
+    pub dead_units: Option<f64>,
+    // fraction of sigmoid / tanh outputs within 5% of their bounds
+    pub saturation: Option<f64>,
+}
+
+/**
+ * counts[i] values fall in [min + i * width, min + (i + 1) * width), the last bin includes max.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Histogram {
+    pub min: f64,
+    pub max: f64,
+    pub counts: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StatsWarningKind {
+    VanishingGradient,
+    ExplodingGradient,
+    DeadUnits,
+    Saturation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsWarning {
+    pub layer: usize,
+    pub kind: StatsWarningKind,
+    pub value: f64,
+    pub message: String,
+}
+
+impl<T: Float> NeuralNetwork<T> {
+    /**
+     * Weight, gradient and activation statistics of every layer after the last epoch.
+     *
+     * dead and saturated units are measured on the first training samples.
+     */
+    #[allow(dead_code)]
+    pub fn layer_stats(&self, thresholds: &StatsThresholds) -> NetworkStats {
+        let samples = &self.training_data[..self.training_data.len().min(STATS_SAMPLES)];
+        let mut layer_inputs = Matrix::from_rows(
+            &samples
+                .iter()
+                .map(|(inputs, _)| inputs.as_slice())
+                .collect::<Vec<_>>(),
+        );
+
+        let layers = self
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(l, layer)| {
+                let weights = layer.weights.data();
+                let n = weights.len().max(1) as f64;
+                let weight_mean = weights.iter().map(|w| w.as_f64()).sum::<f64>() / n;
+                let variance = weights
+                    .iter()
+                    .map(|w| (w.as_f64() - weight_mean).powi(2))
+                    .sum::<f64>()
+                    / n;
+
+                let (dead_units, saturation) = if samples.is_empty() {
+                    (None, None)
+                } else {
+                    let x = layer.weighted_sums(&layer_inputs);
+                    layer_inputs = layer.activate(&x);
+                    (
+                        dead_units(&layer.activation_function, &x),
+                        saturation(&layer.activation_function, &layer_inputs),
+                    )
+                };
+
+                LayerStats {
+                    weight_mean,
+                    weight_std: variance.sqrt(),
+                    weight_histogram: Histogram::new(weights, thresholds.histogram_bins),
+                    gradient_norm: self.layer_gradient_norms.get(l).copied().unwrap_or(0.0),
+                    dead_units,
+                    saturation,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let warnings = layers
+            .iter()
+            .enumerate()
+            .flat_map(|(l, stats)| stats.warnings(l, thresholds))
+            .collect();
+        NetworkStats { layers, warnings }
+    }
+}
+
+impl LayerStats {
+    fn warnings(&self, layer: usize, thresholds: &StatsThresholds) -> Vec<StatsWarning> {
+        let warning = |kind, value, message: &str| StatsWarning {
+            layer,
+            kind,
+            value,
+            message: format!("Layer {}: {}", layer + 1, message),
+        };
+        let mut warnings = vec![];
+        // a zero norm means the network was not trained yet
+        if self.gradient_norm > 0.0 && self.gradient_norm < thresholds.vanishing_gradient {
+            warnings.push(warning(
+                StatsWarningKind::VanishingGradient,
+                self.gradient_norm,
+                "gradients vanished",
+            ));
+        }
+        if self.gradient_norm.is_nan() || self.gradient_norm > thresholds.exploding_gradient {
+            warnings.push(warning(
+                StatsWarningKind::ExplodingGradient,
+                self.gradient_norm,
+                "gradients exploded",
+            ));
+        }
+        if let Some(dead_units) = self.dead_units.filter(|&d| d >= thresholds.dead_units) {
+            warnings.push(warning(
+                StatsWarningKind::DeadUnits,
+                dead_units,
+                "too many ReLU units never activate",
+            ));
+        }
+        if let Some(saturation) = self.saturation.filter(|&s| s >= thresholds.saturation) {
+            warnings.push(warning(
+                StatsWarningKind::Saturation,
+                saturation,
+                "too many outputs are saturated",
+            ));
+        }
+        warnings
+    }
+}
+
+impl Histogram {
+    pub fn new<T: Float>(values: &[T], bins: usize) -> Self {
+        let bins = bins.max(1);
+        let (min, max) = values
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+                (min.min(v.as_f64()), max.max(v.as_f64()))
+            });
+        let mut counts = vec![0; bins];
+        if values.is_empty() {
+            return Histogram {
+                min: 0.0,
+                max: 0.0,
+                counts,
+            };
+        }
+        let width = (max - min) / bins as f64;
+        values.iter().for_each(|v| {
+            let bin = if width > 0.0 {
+                ((v.as_f64() - min) / width) as usize
+            } else {
+                0
+            };
+            counts[bin.min(bins - 1)] += 1;
+        });
+        Histogram { min, max, counts }
+    }
+}
+
+fn dead_units<T: Float>(activation_function: &ActivationFunction, x: &Matrix<T>) -> Option<f64> {
+    if !matches!(activation_function, ActivationFunction::Relu) {
+        return None;
+    }
+    let dead = (0..x.cols())
+        .filter(|&j| x.iter_rows().all(|row| row[j] <= T::zero()))
+        .count();
+    Some(dead as f64 / x.cols().max(1) as f64)
+}
+
+fn saturation<T: Float>(activation_function: &ActivationFunction, y: &Matrix<T>) -> Option<f64> {
+    let saturated: fn(f64) -> bool = match activation_function {
+        ActivationFunction::Sigmoid => |y| !(0.05..=0.95).contains(&y),
+        ActivationFunction::Tanh => |y| y.abs() > 0.95,
+        _ => return None,
+    };
+    let count = y.data().iter().filter(|v| saturated(v.as_f64())).count();
+    Some(count as f64 / y.data().len().max(1) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_includes_the_bounds() {
+        let histogram = Histogram::new(&[-1.0, -0.5, 0.0, 0.5, 1.0], 4);
+        assert_eq!((histogram.min, histogram.max), (-1.0, 1.0));
+        assert_eq!(histogram.counts, vec![1, 1, 1, 2]);
+    }
+
+    #[test]
+    fn dead_relu_units_are_reported() {
+        // the second neuron never has a positive weighted sum
+        let x = Matrix::from_rows(&[[0.5, -1.0], [-0.2, 0.0], [1.0, -3.0]]);
+        assert_eq!(dead_units(&ActivationFunction::Relu, &x), Some(0.5));
+        assert_eq!(dead_units(&ActivationFunction::Tanh, &x), None);
+    }
+}
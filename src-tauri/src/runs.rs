@@ -0,0 +1,395 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    nn::{
+        metrics::{EvaluationReport, Metric, MultiLabelReport, RegressionReport},
+        model::Model,
+        Split, TaskType,
+    },
+    Settings,
+};
+
+// the data of the settings, left out of comparisons (the fingerprint covers it) and searches
+pub const DATA_SETTINGS: [&str; 3] = ["trainingData", "validationData", "testingData"];
+
+/**
+ * Everything needed to reproduce, reload and compare one training run.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunRecord {
+    pub id: String,
+    // unix time in milliseconds
+    pub created_at: u64,
+    pub settings: Settings,
+    // hash of the training, validation and testing data
+    pub fingerprint: String,
+    // the seed the network was built with, only missing in runs saved before seeds were drawn
+    pub seed: Option<u64>,
+    pub epochs: usize,
+    pub mse_history: Vec<f64>,
+    pub mse_validation_history: Vec<f64>,
+    pub metric_history: BTreeMap<Metric, Vec<f64>>,
+    pub evaluation: Option<Vec<EvaluationReport>>,
+    pub multi_label_report: Option<MultiLabelReport>,
+    pub regression_report: Option<RegressionReport>,
+    pub model: Model,
+}
+
+/**
+ * What the run list shows of a record.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSummary {
+    pub id: String,
+    pub created_at: u64,
+    pub fingerprint: String,
+    pub seed: Option<u64>,
+    pub task_type: TaskType,
+    pub epochs: usize,
+    // of the last epoch
+    pub mse: Option<f64>,
+    pub mse_validation: Option<f64>,
+    // of the testing data, classification only
+    pub accuracy: Option<f64>,
+}
+
+/**
+ * Runs side by side, histories are padded with None to the longest run.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunComparison {
+    pub runs: Vec<RunSummary>,
+    // whether all runs were trained on the same data
+    pub same_data: bool,
+    // [run][epoch]
+    pub mse_history: Vec<Vec<Option<f64>>>,
+    pub mse_validation_history: Vec<Vec<Option<f64>>>,
+    pub metric_history: BTreeMap<Metric, Vec<Vec<Option<f64>>>>,
+    // flattened setting path (e.g. hiddenLayers.0.neuronsCount) to its value in every run
+    pub settings_differences: BTreeMap<String, Vec<Value>>,
+}
+
+/**
+ * One JSON file per run in a directory, next to a small <id>.summary.json read by list.
+ */
+pub struct RunStore {
+    dir: PathBuf,
+}
+
+impl RunRecord {
+    pub fn summary(&self) -> RunSummary {
+        let accuracy = self.evaluation.as_ref().and_then(|reports| {
+            reports
+                .iter()
+                .find(|r| r.split == Split::Testing)
+                .map(|r| r.accuracy)
+        });
+        RunSummary {
+            id: self.id.clone(),
+            created_at: self.created_at,
+            fingerprint: self.fingerprint.clone(),
+            seed: self.seed,
+            task_type: self.model.task_type,
+            epochs: self.epochs,
+            mse: self.mse_history.last().copied(),
+            mse_validation: self.mse_validation_history.last().copied(),
+            accuracy,
+        }
+    }
+}
+
+impl RunStore {
+    pub fn new(dir: PathBuf) -> Self {
+        RunStore { dir }
+    }
+
+    /**
+     * A new unique id based on the current time.
+     */
+    pub fn next_id(&self) -> (String, u64) {
+        let mut created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        while self.path(&format!("run-{}", created_at)).exists() {
+            created_at += 1;
+        }
+        (format!("run-{}", created_at), created_at)
+    }
+
+    pub fn save(&self, record: &RunRecord) -> Result<(), String> {
+        validate_id(&record.id)?;
+        std::fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        write_json(&self.path(&record.id), record)?;
+        write_json(&self.summary_path(&record.id), &record.summary())
+    }
+
+    pub fn load(&self, id: &str) -> Result<RunRecord, String> {
+        validate_id(id)?;
+        let json = std::fs::read_to_string(self.path(id))
+            .map_err(|e| format!("Cannot read run {}: {}", id, e))?;
+        serde_json::from_str(&json).map_err(|e| format!("Cannot read run {}: {}", id, e))
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        validate_id(id)?;
+        std::fs::remove_file(self.path(id))
+            .map_err(|e| format!("Cannot delete run {}: {}", id, e))?;
+        // runs saved before summaries existed have none
+        let _ = std::fs::remove_file(self.summary_path(id));
+        Ok(())
+    }
+
+    /**
+     * Summaries of every readable run, oldest first.
+     */
+    pub fn list(&self) -> Result<Vec<RunSummary>, String> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut runs = std::fs::read_dir(&self.dir)
+            .map_err(|e| e.to_string())?
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name().into_string().ok()?;
+                // summaries have a dot in their stem and are not valid ids
+                let id = name.strip_suffix(".json")?;
+                validate_id(id).ok()?;
+                self.summary(id).ok()
+            })
+            .collect::<Vec<_>>();
+        runs.sort_by_key(|run| run.created_at);
+        Ok(runs)
+    }
+
+    // the summary of a run, written from the full record when it has none yet
+    fn summary(&self, id: &str) -> Result<RunSummary, String> {
+        if let Ok(json) = std::fs::read_to_string(self.summary_path(id)) {
+            return serde_json::from_str(&json).map_err(|e| e.to_string());
+        }
+        let summary = self.load(id)?.summary();
+        let _ = write_json(&self.summary_path(id), &summary);
+        Ok(summary)
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn summary_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.summary.json", id))
+    }
+}
+
+impl RunComparison {
+    pub fn new(records: &[RunRecord]) -> Self {
+        let metrics = records
+            .iter()
+            .flat_map(|r| r.metric_history.keys().copied())
+            .collect::<BTreeSet<_>>();
+        let settings = records
+            .iter()
+            .map(|r| flatten_settings(&r.settings))
+            .collect::<Vec<_>>();
+        let paths = settings
+            .iter()
+            .flat_map(|s| s.keys().cloned())
+            .collect::<BTreeSet<_>>();
+
+        RunComparison {
+            runs: records.iter().map(|r| r.summary()).collect(),
+            same_data: records
+                .windows(2)
+                .all(|w| w[0].fingerprint == w[1].fingerprint),
+            mse_history: align(
+                &records
+                    .iter()
+                    .map(|r| &r.mse_history[..])
+                    .collect::<Vec<_>>(),
+            ),
+            mse_validation_history: align(
+                &records
+                    .iter()
+                    .map(|r| &r.mse_validation_history[..])
+                    .collect::<Vec<_>>(),
+            ),
+            metric_history: metrics
+                .into_iter()
+                .map(|metric| {
+                    let histories = records
+                        .iter()
+                        .map(|r| r.metric_history.get(&metric).map_or(&[][..], |h| &h[..]))
+                        .collect::<Vec<_>>();
+                    (metric, align(&histories))
+                })
+                .collect(),
+            settings_differences: paths
+                .into_iter()
+                .filter_map(|path| {
+                    let values = settings
+                        .iter()
+                        .map(|s| s.get(&path).cloned().unwrap_or(Value::Null))
+                        .collect::<Vec<_>>();
+                    let differ = values.windows(2).any(|w| w[0] != w[1]);
+                    differ.then_some((path, values))
+                })
+                .collect(),
+        }
+    }
+}
+
+/**
+ * Pads every history with None to the longest one, so index i is epoch i in all of them.
+ */
+pub fn align(histories: &[&[f64]]) -> Vec<Vec<Option<f64>>> {
+    let epochs = histories.iter().map(|h| h.len()).max().unwrap_or(0);
+    histories
+        .iter()
+        .map(|h| (0..epochs).map(|epoch| h.get(epoch).copied()).collect())
+        .collect()
+}
+
+/**
+ * FNV-1a hash of the bits of every data value, the splits are hashed in order and separated
+ * so moving a sample to another split changes the fingerprint.
+ */
+pub fn fingerprint(splits: &[&[Vec<f64>]]) -> String {
+    const OFFSET: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    let mut hash = OFFSET;
+    let mut write = |value: u64| {
+        value.to_le_bytes().iter().for_each(|&byte| {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(PRIME);
+        })
+    };
+    splits.iter().for_each(|rows| {
+        write(rows.len() as u64);
+        rows.iter().for_each(|row| {
+            write(row.len() as u64);
+            row.iter().for_each(|v| write(v.to_bits()));
+        });
+    });
+    format!("{:016x}", hash)
+}
+
+// every leaf of the settings (without the data) under its dotted path
+fn flatten_settings(settings: &Settings) -> BTreeMap<String, Value> {
+    let mut value = serde_json::to_value(settings).unwrap_or(Value::Null);
+    if let Value::Object(map) = &mut value {
+        DATA_SETTINGS.iter().for_each(|key| {
+            map.remove(*key);
+        });
+    }
+    let mut leaves = BTreeMap::new();
+    flatten(&value, String::new(), &mut leaves);
+    leaves
+}
+
+fn flatten(value: &Value, path: String, leaves: &mut BTreeMap<String, Value>) {
+    let child = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match value {
+        Value::Object(map) => map
+            .iter()
+            .for_each(|(key, v)| flatten(v, child(key), leaves)),
+        Value::Array(values) => values
+            .iter()
+            .enumerate()
+            .for_each(|(i, v)| flatten(v, child(&i.to_string()), leaves)),
+        _ => {
+            leaves.insert(path, value.clone());
+        }
+    }
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> Result<(), String> {
+    let json = serde_json::to_string(value).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())
+}
+
+// ids become file names, only letters, digits and dashes are allowed
+fn validate_id(id: &str) -> Result<(), String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid run id: {}", id));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_depends_on_values_and_splits() {
+        let a = vec![vec![1.0, 2.0, 0.0], vec![3.0, 4.0, 1.0]];
+        let b = vec![vec![5.0, 6.0, 1.0]];
+        let base = fingerprint(&[&a, &b, &[]]);
+        assert_eq!(base, fingerprint(&[&a, &b, &[]]));
+
+        let mut changed = a.clone();
+        changed[1][0] = 3.000001;
+        assert_ne!(base, fingerprint(&[&changed, &b, &[]]));
+        // the same samples in another split
+        assert_ne!(base, fingerprint(&[&a, &[], &b]));
+    }
+
+    #[test]
+    fn runs_round_trip_through_the_store() {
+        let dir = std::env::temp_dir().join(format!("runs-{}", std::process::id()));
+        let store = RunStore::new(dir.clone());
+        let settings = crate::search::tests::settings();
+        let nn = crate::build_network(&settings).unwrap();
+        let (id, created_at) = store.next_id();
+        let record = RunRecord {
+            id: id.clone(),
+            created_at,
+            settings,
+            fingerprint: fingerprint(&[]),
+            seed: nn.seed(),
+            epochs: 1,
+            mse_history: vec![0.5],
+            mse_validation_history: vec![0.6],
+            metric_history: BTreeMap::new(),
+            evaluation: None,
+            multi_label_report: None,
+            regression_report: None,
+            model: nn.to_model(),
+        };
+        store.save(&record).unwrap();
+
+        let loaded = store.load(&id).unwrap();
+        assert_eq!(loaded.seed, Some(7));
+        assert_eq!(loaded.mse_validation_history, vec![0.6]);
+        // list only reads the summaries
+        std::fs::write(store.path(&id), "{}").unwrap();
+        let runs = store.list().unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!((runs[0].id.as_str(), runs[0].mse), (id.as_str(), Some(0.5)));
+
+        store.delete(&id).unwrap();
+        assert!(store.list().unwrap().is_empty());
+        assert!(store.load(&id).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn run_ids_cannot_leave_the_store() {
+        assert!(validate_id("run-1700000000000").is_ok());
+        assert!(validate_id("../settings").is_err());
+        assert!(validate_id("").is_err());
+    }
+}
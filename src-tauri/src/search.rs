@@ -0,0 +1,683 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    build_network,
+    networks::NamedNetwork,
+    nn::{metrics::Metric, model::Model, TaskType},
+    runs::DATA_SETTINGS,
+    tuning, Settings,
+};
+
+// grids larger than this are refused, random search samples big spaces instead
+pub const MAX_TRIALS: usize = 10_000;
+
+/**
+ * Values a setting takes during the search.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ParameterSpace {
+    // any JSON the setting accepts, e.g. a whole hiddenLayers array
+    Choice {
+        values: Vec<Value>,
+    },
+    // grid search takes steps evenly spaced values, random search draws uniformly
+    #[serde(rename_all = "camelCase")]
+    Range {
+        min: f64,
+        max: f64,
+        #[serde(default)]
+        steps: usize,
+        // spaced (or drawn) evenly in log scale, for learning rates
+        #[serde(default)]
+        log: bool,
+        // rounded, for neuron counts and batch sizes
+        #[serde(default)]
+        integer: bool,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchParameter {
+    // dotted path of the setting, e.g. alpha or hiddenLayers.0.neuronsCount
+    pub path: String,
+    pub space: ParameterSpace,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SearchStrategy {
+    // every combination of the values
+    Grid,
+    Random {
+        trials: usize,
+    },
+    // tree-structured Parzen estimator, after initial random trials (10 when 0) every trial
+    // is drawn where the best trials so far are dense
+    Bayesian {
+        trials: usize,
+        #[serde(default)]
+        initial: usize,
+    },
+    // random trials trained min_epochs, then the best 1 / eta of them eta times longer,
+    // until max epochs, eta defaults to 3
+    #[serde(rename_all = "camelCase")]
+    SuccessiveHalving {
+        trials: usize,
+        min_epochs: usize,
+        #[serde(default)]
+        eta: usize,
+    },
+    // successive halving brackets from many short trials to a few full length ones
+    Hyperband {
+        #[serde(default)]
+        eta: usize,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchSettings {
+    pub parameters: Vec<SearchParameter>,
+    pub strategy: SearchStrategy,
+    // validation metric ranking the trials, the validation MSE when None
+    #[serde(default)]
+    pub objective: Option<Metric>,
+    // epochs of every trial, the max epochs of the settings when None
+    #[serde(default)]
+    pub max_epochs: Option<usize>,
+    // makes random search draw the same trials
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrialResult {
+    pub index: usize,
+    // value of every searched setting
+    pub values: BTreeMap<String, Value>,
+    // objective after the last epoch, None when the trial failed
+    pub score: Option<f64>,
+    pub epochs: usize,
+    pub mse_validation: Option<f64>,
+    pub diverged: bool,
+    // stopped early by successive halving, ranked after the complete trials
+    pub pruned: bool,
+    // invalid settings or a stopped search
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BestTrial {
+    pub index: usize,
+    pub settings: Settings,
+    pub model: Model,
+}
+
+/**
+ * The trial with the lowest loss so far, the only network of a search that is kept until the
+ * end. Pruned trials and trials without a score never lead.
+ */
+#[derive(Default)]
+pub struct Leader(Mutex<Option<(f64, usize, NamedNetwork)>>);
+
+/**
+ * Trials ranked best first, failed trials last.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub objective: Option<Metric>,
+    pub trials: Vec<TrialResult>,
+    pub best: Option<BestTrial>,
+}
+
+impl ParameterSpace {
+    fn validate(&self) -> Result<(), String> {
+        match *self {
+            ParameterSpace::Choice { ref values } if values.is_empty() => {
+                Err("A choice needs at least one value".to_string())
+            }
+            ParameterSpace::Range { min, max, log, .. } => {
+                if min.is_nan() || max.is_nan() || min > max {
+                    return Err("A range needs min <= max".to_string());
+                }
+                if log && min <= 0.0 {
+                    return Err("A log range needs min > 0".to_string());
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // the values of a grid search
+    fn grid(&self) -> Vec<Value> {
+        match *self {
+            ParameterSpace::Choice { ref values } => values.clone(),
+            ParameterSpace::Range {
+                min,
+                max,
+                steps,
+                log,
+                integer,
+            } => {
+                let steps = steps.max(1);
+                let mut values = (0..steps)
+                    .map(|i| {
+                        let t = if steps > 1 {
+                            i as f64 / (steps - 1) as f64
+                        } else {
+                            0.0
+                        };
+                        range_value(min, max, t, log, integer)
+                    })
+                    .collect::<Vec<_>>();
+                values.dedup();
+                values
+            }
+        }
+    }
+
+    /**
+     * A uniformly drawn coordinate, 0..1 in a range and the value index in a choice.
+     */
+    pub fn sample(&self, rng: &mut StdRng) -> f64 {
+        match *self {
+            ParameterSpace::Choice { ref values } => rng.gen_range(0..values.len()) as f64,
+            ParameterSpace::Range { .. } => rng.gen::<f64>(),
+        }
+    }
+
+    pub fn value_at(&self, coordinate: f64) -> Value {
+        match *self {
+            ParameterSpace::Choice { ref values } => {
+                values[(coordinate as usize).min(values.len() - 1)].clone()
+            }
+            ParameterSpace::Range {
+                min,
+                max,
+                log,
+                integer,
+                ..
+            } => range_value(min, max, coordinate.clamp(0.0, 1.0), log, integer),
+        }
+    }
+}
+
+impl SearchSettings {
+    /**
+     * Checks the spaces and that every path exists in the base settings.
+     */
+    pub fn validate(&self, base: &Settings) -> Result<(), String> {
+        if self.parameters.is_empty() {
+            return Err("The search needs at least one parameter".to_string());
+        }
+        let base = serde_json::to_value(base).map_err(|e| e.to_string())?;
+        self.parameters.iter().try_for_each(|parameter| {
+            let root = parameter.path.split('.').next().unwrap_or_default();
+            if DATA_SETTINGS.contains(&root) {
+                return Err(format!("{} cannot be searched", parameter.path));
+            }
+            base.pointer(&pointer(&parameter.path))
+                .ok_or(format!("Unknown setting {}", parameter.path))?;
+            parameter.space.validate()
+        })?;
+        validate_objective(self.objective, base_task_type(&base))
+    }
+
+    /**
+     * The values of every trial, all grid combinations or the random draws.
+     */
+    pub fn candidates(&self) -> Result<Vec<BTreeMap<String, Value>>, String> {
+        match self.strategy {
+            SearchStrategy::Grid => {
+                let grids = self
+                    .parameters
+                    .iter()
+                    .map(|p| (p.path.clone(), p.space.grid()))
+                    .collect::<Vec<_>>();
+                let trials = grids
+                    .iter()
+                    .try_fold(1usize, |n, (_, values)| n.checked_mul(values.len()))
+                    .filter(|&n| n <= MAX_TRIALS)
+                    .ok_or(format!(
+                        "The grid has more than {} trials, use random search",
+                        MAX_TRIALS
+                    ))?;
+                // the first parameter changes slowest
+                Ok((0..trials)
+                    .map(|mut i| {
+                        let mut values = BTreeMap::new();
+                        grids.iter().rev().for_each(|(path, grid)| {
+                            values.insert(path.clone(), grid[i % grid.len()].clone());
+                            i /= grid.len();
+                        });
+                        values
+                    })
+                    .collect())
+            }
+            SearchStrategy::Random { trials } => {
+                if !(1..=MAX_TRIALS).contains(&trials) {
+                    return Err(format!("Random search needs 1 to {} trials", MAX_TRIALS));
+                }
+                let mut rng = self.rng();
+                Ok((0..trials)
+                    .map(|_| self.values_at(&self.sample(&mut rng)))
+                    .collect())
+            }
+            _ => Err("This strategy draws its trials while searching".to_string()),
+        }
+    }
+
+    pub fn rng(&self) -> StdRng {
+        self.seed
+            .map_or_else(StdRng::from_entropy, StdRng::seed_from_u64)
+    }
+
+    // one uniformly drawn coordinate per parameter
+    pub fn sample(&self, rng: &mut StdRng) -> Vec<f64> {
+        self.parameters
+            .iter()
+            .map(|p| p.space.sample(rng))
+            .collect()
+    }
+
+    pub fn values_at(&self, point: &[f64]) -> BTreeMap<String, Value> {
+        self.parameters
+            .iter()
+            .zip(point)
+            .map(|(p, &coordinate)| (p.path.clone(), p.space.value_at(coordinate)))
+            .collect()
+    }
+}
+
+/**
+ * The base settings with the values of a trial.
+ */
+pub fn apply(base: &Settings, values: &BTreeMap<String, Value>) -> Result<Settings, String> {
+    let mut settings = serde_json::to_value(base).map_err(|e| e.to_string())?;
+    values.iter().try_for_each(|(path, value)| {
+        *settings
+            .pointer_mut(&pointer(path))
+            .ok_or(format!("Unknown setting {}", path))? = value.clone();
+        Ok::<_, String>(())
+    })?;
+    serde_json::from_value(settings).map_err(|e| format!("Invalid settings: {}", e))
+}
+
+/**
+ * Objective of a network, None when it cannot be computed (e.g. no validation data).
+ */
+pub fn score(network: &NamedNetwork, objective: Option<Metric>) -> Option<f64> {
+    match objective {
+        None => network.history.mse_validation.last().copied(),
+        Some(metric) => network.nn.metric(metric),
+    }
+    .filter(|score| score.is_finite())
+}
+
+/**
+ * The score turned into a loss, lower is better for every objective.
+ */
+pub fn loss(score: f64, objective: Option<Metric>) -> f64 {
+    match objective {
+        Some(Metric::ValidationAccuracy | Metric::ValidationF1) => -score,
+        _ => score,
+    }
+}
+
+/**
+ * Trains every trial on the data of the base settings, in parallel, and ranks them.
+ *
+ * setting cancel stops the running trials after their current epoch.
+ */
+pub fn search(
+    base: &Settings,
+    search: &SearchSettings,
+    cancel: &AtomicBool,
+) -> Result<SearchResult, String> {
+    search.validate(base)?;
+    if base.validation_data.is_empty() {
+        return Err("The search ranks trials on the validation data, add some".to_string());
+    }
+    let max_epochs = search.max_epochs.unwrap_or(base.max_epochs);
+
+    let (trials, leader) = match search.strategy {
+        SearchStrategy::Grid | SearchStrategy::Random { .. } => {
+            let leader = Leader::default();
+            let trials = search
+                .candidates()?
+                .into_par_iter()
+                .enumerate()
+                .map(|(index, values)| {
+                    let network = run_trial(base, &values, max_epochs, cancel);
+                    TrialResult::new(index, values, network, search.objective, false, &leader)
+                })
+                .collect();
+            (trials, leader)
+        }
+        SearchStrategy::Bayesian { trials, initial } => {
+            tuning::tpe(base, search, trials, initial, max_epochs, cancel)?
+        }
+        SearchStrategy::SuccessiveHalving {
+            trials,
+            min_epochs,
+            eta,
+        } => {
+            let mut rng = search.rng();
+            let points = (0..trials).map(|_| search.sample(&mut rng)).collect();
+            tuning::successive_halving(base, search, points, min_epochs, max_epochs, eta, cancel)?
+        }
+        SearchStrategy::Hyperband { eta } => {
+            tuning::hyperband(base, search, max_epochs, eta, cancel)?
+        }
+    };
+    Ok(rank(trials, search.objective, leader))
+}
+
+/**
+ * Builds the network of a trial without training it.
+ */
+pub fn build_trial(
+    base: &Settings,
+    values: &BTreeMap<String, Value>,
+) -> Result<NamedNetwork, String> {
+    let settings = apply(base, values)?;
+    let nn = build_network(&settings)?;
+    Ok(NamedNetwork::new(nn, Some(settings)))
+}
+
+pub fn run_trial(
+    base: &Settings,
+    values: &BTreeMap<String, Value>,
+    max_epochs: usize,
+    cancel: &AtomicBool,
+) -> Result<NamedNetwork, String> {
+    let mut network = build_trial(base, values)?;
+    train(&mut network, max_epochs, cancel)?;
+    Ok(network)
+}
+
+impl TrialResult {
+    /**
+     * The outcome of a trained (or failed) trial, its network is offered to the leader.
+     */
+    pub fn new(
+        index: usize,
+        values: BTreeMap<String, Value>,
+        network: Result<NamedNetwork, String>,
+        objective: Option<Metric>,
+        pruned: bool,
+        leader: &Leader,
+    ) -> Self {
+        let mut trial = TrialResult {
+            index,
+            values,
+            score: None,
+            epochs: 0,
+            mse_validation: None,
+            diverged: false,
+            pruned,
+            error: None,
+        };
+        match network {
+            Ok(network) => {
+                trial.epochs = network.history.mse.len();
+                trial.mse_validation = network.history.mse_validation.last().copied();
+                trial.diverged = network.history.diverged;
+                trial.score = score(&network, objective).filter(|_| !trial.diverged);
+                if let Some(score) = trial.score.filter(|_| !pruned) {
+                    leader.offer(loss(score, objective), index, network);
+                }
+            }
+            Err(e) => trial.error = Some(e),
+        }
+        trial
+    }
+}
+
+impl Leader {
+    // keeps the network when it beats the leader, the lowest index wins a tie like in rank
+    fn offer(&self, loss: f64, index: usize, network: NamedNetwork) {
+        let mut leader = self.0.lock().unwrap();
+        let leads = leader.as_ref().is_none_or(|(best_loss, best_index, _)| {
+            loss.total_cmp(best_loss)
+                .then(index.cmp(best_index))
+                .is_lt()
+        });
+        if leads {
+            *leader = Some((loss, index, network));
+        }
+    }
+
+    /**
+     * Offers the leader of trials numbered from first, e.g. a bracket of hyperband.
+     */
+    pub fn merge(&self, other: Leader, first: usize) {
+        if let Some((loss, index, network)) = other.0.into_inner().unwrap() {
+            self.offer(loss, first + index, network);
+        }
+    }
+
+    pub(crate) fn into_best(self) -> Option<BestTrial> {
+        let (_, index, network) = self.0.into_inner().unwrap()?;
+        Some(BestTrial {
+            index,
+            model: network.nn.to_model(),
+            settings: network.settings?,
+        })
+    }
+}
+
+/**
+ * Sorts the trials best first (pruned ones after the complete ones), the best one keeps the
+ * settings and model of the leader.
+ */
+pub fn rank(
+    mut trials: Vec<TrialResult>,
+    objective: Option<Metric>,
+    leader: Leader,
+) -> SearchResult {
+    trials.sort_by(|a, b| {
+        let key = |t: &TrialResult| t.score.map_or(f64::INFINITY, |s| loss(s, objective));
+        (a.pruned.cmp(&b.pruned))
+            .then(key(a).total_cmp(&key(b)))
+            .then(a.index.cmp(&b.index))
+    });
+    SearchResult {
+        objective,
+        trials,
+        best: leader.into_best(),
+    }
+}
+
+/**
+ * Trains up to max_epochs epochs, stops early at the desired MSE of the settings.
+ */
+pub fn train(
+    network: &mut NamedNetwork,
+    max_epochs: usize,
+    cancel: &AtomicBool,
+) -> Result<(), String> {
+    let desired_mse = network.settings.as_ref().map_or(0.0, |s| s.desired_mse);
+    for _ in network.history.mse.len()..max_epochs {
+        if cancel.load(Ordering::Relaxed) {
+            return Err("Search stopped".to_string());
+        }
+        match network.epoch(&[]) {
+            Some(values) if values.mse > desired_mse => {}
+            _ => break,
+        }
+    }
+    Ok(())
+}
+
+// the objective must be measured on the validation data
+fn validate_objective(objective: Option<Metric>, task_type: TaskType) -> Result<(), String> {
+    match objective {
+        None => Ok(()),
+        Some(
+            Metric::ValidationAccuracy | Metric::ValidationCrossEntropy | Metric::ValidationF1,
+        ) => {
+            if task_type == TaskType::Regression {
+                Err("Regression searches rank trials on the validation MSE".to_string())
+            } else {
+                Ok(())
+            }
+        }
+        Some(metric) => Err(format!("{:?} is not a validation metric", metric)),
+    }
+}
+
+fn base_task_type(base: &Value) -> TaskType {
+    base.get("taskType")
+        .and_then(|t| serde_json::from_value(t.clone()).ok())
+        .unwrap_or_default()
+}
+
+// the value at t in 0..1 of a range
+fn range_value(min: f64, max: f64, t: f64, log: bool, integer: bool) -> Value {
+    let v = if log {
+        (min.ln() + t * (max.ln() - min.ln())).exp()
+    } else {
+        min + t * (max - min)
+    };
+    if integer {
+        Value::from(v.round() as i64)
+    } else {
+        Value::from(v)
+    }
+}
+
+// JSON pointer of a dotted path
+fn pointer(path: &str) -> String {
+    format!("/{}", path.replace('.', "/"))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use serde_json::json;
+
+    pub(crate) fn settings() -> Settings {
+        let data = (0..40)
+            .map(|i| {
+                let class = (i % 2) as f64;
+                vec![i as f64, class * 10.0, 1.0 - class, class]
+            })
+            .collect::<Vec<_>>();
+        serde_json::from_value(json!({
+            "layersCount": 3,
+            "hiddenLayers": [{ "neuronsCount": 4, "activationFunction": "tanh" }],
+            "outputLayer": { "neuronsCount": 2, "activationFunction": "softmax" },
+            "alpha": 0.1,
+            "maxEpochs": 3,
+            "desiredMse": 0.0,
+            "trainingData": data,
+            "validationData": data,
+            "testingData": [],
+            "noise": 0.0,
+            "density": 1.0,
+            "scale": [0.0, 100.0],
+            "seed": 7
+        }))
+        .unwrap()
+    }
+
+    fn parameter(path: &str, space: ParameterSpace) -> SearchParameter {
+        SearchParameter {
+            path: path.to_string(),
+            space,
+        }
+    }
+
+    #[test]
+    fn grid_covers_every_combination() {
+        let search = SearchSettings {
+            parameters: vec![
+                parameter(
+                    "alpha",
+                    ParameterSpace::Range {
+                        min: 0.001,
+                        max: 0.1,
+                        steps: 3,
+                        log: true,
+                        integer: false,
+                    },
+                ),
+                parameter(
+                    "hiddenLayers.0.neuronsCount",
+                    ParameterSpace::Choice {
+                        values: vec![json!(4), json!(8)],
+                    },
+                ),
+            ],
+            strategy: SearchStrategy::Grid,
+            objective: None,
+            max_epochs: None,
+            seed: None,
+        };
+        search.validate(&settings()).unwrap();
+        let candidates = search.candidates().unwrap();
+        assert_eq!(candidates.len(), 6);
+        let alpha = candidates[2]["alpha"].as_f64().unwrap();
+        assert!((alpha - 0.01).abs() < 1e-12);
+        assert_eq!(candidates[3]["hiddenLayers.0.neuronsCount"], json!(8));
+
+        let settings = apply(&settings(), &candidates[3]).unwrap();
+        assert_eq!(settings.hidden_layers[0].neurons_count, 8);
+    }
+
+    #[test]
+    fn random_search_ranks_the_trials() {
+        let search = SearchSettings {
+            parameters: vec![parameter(
+                "alpha",
+                ParameterSpace::Range {
+                    min: 0.01,
+                    max: 0.5,
+                    steps: 0,
+                    log: false,
+                    integer: false,
+                },
+            )],
+            strategy: SearchStrategy::Random { trials: 4 },
+            objective: Some(Metric::ValidationAccuracy),
+            max_epochs: Some(2),
+            seed: Some(1),
+        };
+        let result = super::search(&settings(), &search, &AtomicBool::new(false)).unwrap();
+        assert_eq!(result.trials.len(), 4);
+        assert!(result
+            .trials
+            .windows(2)
+            .all(|w| w[0].score.unwrap() >= w[1].score.unwrap()));
+        assert_eq!(result.best.unwrap().index, result.trials[0].index);
+
+        let unknown = SearchSettings {
+            parameters: vec![parameter(
+                "gamma",
+                ParameterSpace::Choice {
+                    values: vec![json!(1)],
+                },
+            )],
+            ..search
+        };
+        assert!(unknown.validate(&settings()).is_err());
+    }
+}
@@ -0,0 +1,447 @@
+use std::{
+    collections::BTreeMap,
+    f64::consts::PI,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use rand::{rngs::StdRng, Rng};
+use rayon::prelude::*;
+use serde_json::Value;
+
+use crate::{
+    networks::NamedNetwork,
+    search::{
+        build_trial, loss, run_trial, train, Leader, ParameterSpace, SearchSettings, TrialResult,
+        MAX_TRIALS,
+    },
+    Settings,
+};
+
+// random trials of the Bayesian search before the Parzen estimators take over, when not set
+const DEFAULT_INITIAL_TRIALS: usize = 10;
+// fraction of the finished trials the Parzen estimators count as good
+const GAMMA: f64 = 0.25;
+// draws from the good density compared for every parameter of a new trial
+const CANDIDATES: usize = 24;
+// trials proposed together after the initial ones, each sees the others as the worst losses
+const PROPOSAL_BATCH: usize = 4;
+// successive halving keeps 1 / eta of the trials at every rung
+const DEFAULT_ETA: usize = 3;
+
+// the trials in the order they ran, with the leader among them
+type Trials = (Vec<TrialResult>, Leader);
+
+/**
+ * Density over the coordinates of one parameter, a uniform prior plus one kernel per
+ * observation (a gaussian in a range, a count in a choice).
+ */
+struct Parzen {
+    observations: Vec<f64>,
+    // values of a choice, None for a range
+    choices: Option<usize>,
+    bandwidth: f64,
+}
+
+// a trial of successive halving, pruned ones are not trained further
+struct Rung {
+    index: usize,
+    values: BTreeMap<String, Value>,
+    network: Result<NamedNetwork, String>,
+    pruned: bool,
+}
+
+impl Parzen {
+    fn new(space: &ParameterSpace, observations: Vec<f64>) -> Self {
+        let choices = match space {
+            ParameterSpace::Choice { values } => Some(values.len()),
+            ParameterSpace::Range { .. } => None,
+        };
+        // Scott's rule with the standard deviation of a uniform 0..1 sample
+        let bandwidth = 0.29 * (observations.len().max(1) as f64).powf(-0.2);
+        Parzen {
+            observations,
+            choices,
+            bandwidth,
+        }
+    }
+
+    fn density(&self, x: f64) -> f64 {
+        let n = self.observations.len() as f64;
+        match self.choices {
+            Some(k) => {
+                let count = self.observations.iter().filter(|&&o| o == x).count();
+                (count as f64 + 1.0) / (n + k as f64)
+            }
+            None => {
+                let h = self.bandwidth;
+                let kernels = self
+                    .observations
+                    .iter()
+                    .map(|o| (-0.5 * ((x - o) / h).powi(2)).exp() / (h * (2.0 * PI).sqrt()))
+                    .sum::<f64>();
+                (1.0 + kernels) / (n + 1.0)
+            }
+        }
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> f64 {
+        let n = self.observations.len();
+        match self.choices {
+            // an observation is as likely as one prior count of every value
+            Some(k) => match rng.gen_range(0..n + k) {
+                i if i < n => self.observations[i],
+                i => (i - n) as f64,
+            },
+            None => match rng.gen_range(0..=n) {
+                i if i < n => {
+                    (self.observations[i] + self.bandwidth * gaussian(rng)).clamp(0.0, 1.0)
+                }
+                _ => rng.gen(),
+            },
+        }
+    }
+}
+
+/**
+ * Tree-structured Parzen estimator search.
+ *
+ * the initial random trials run in one parallel batch, then every batch of PROPOSAL_BATCH
+ * trials is drawn from the results of the previous ones (constant liar: a proposal counts the
+ * pending ones of its batch as the worst loss so far, which spreads the batch).
+ */
+pub fn tpe(
+    base: &Settings,
+    search: &SearchSettings,
+    trials: usize,
+    initial: usize,
+    max_epochs: usize,
+    cancel: &AtomicBool,
+) -> Result<Trials, String> {
+    if !(1..=MAX_TRIALS).contains(&trials) {
+        return Err(format!("Bayesian search needs 1 to {} trials", MAX_TRIALS));
+    }
+    let initial = if initial == 0 {
+        DEFAULT_INITIAL_TRIALS
+    } else {
+        initial
+    };
+    let mut rng = search.rng();
+    // coordinates and loss of every finished trial, failed trials count as the worst
+    let mut observed: Vec<(Vec<f64>, f64)> = vec![];
+    let mut results = vec![];
+    let leader = Leader::default();
+
+    while results.len() < trials && !cancel.load(Ordering::Relaxed) {
+        let points = if observed.is_empty() {
+            (0..initial.min(trials))
+                .map(|_| search.sample(&mut rng))
+                .collect::<Vec<_>>()
+        } else {
+            let worst = observed
+                .iter()
+                .map(|(_, loss)| *loss)
+                .fold(f64::MIN, f64::max);
+            let mut lied = observed.clone();
+            (0..PROPOSAL_BATCH.min(trials - results.len()))
+                .map(|_| {
+                    let point = propose(search, &lied, &mut rng);
+                    lied.push((point.clone(), worst));
+                    point
+                })
+                .collect()
+        };
+        let first = results.len();
+        let finished = points
+            .into_par_iter()
+            .enumerate()
+            .map(|(i, point)| {
+                let values = search.values_at(&point);
+                let network = run_trial(base, &values, max_epochs, cancel);
+                let trial =
+                    TrialResult::new(first + i, values, network, search.objective, false, &leader);
+                (point, trial)
+            })
+            .collect::<Vec<_>>();
+        finished.into_iter().for_each(|(point, trial)| {
+            let trial_loss = trial
+                .score
+                .map_or(f64::INFINITY, |s| loss(s, search.objective));
+            observed.push((point, trial_loss));
+            results.push(trial);
+        });
+    }
+    Ok((results, leader))
+}
+
+/**
+ * Trains every point min_epochs epochs, then the best 1 / eta of them (by their last
+ * validation MSE) eta times longer and so on until max_epochs, the others are pruned.
+ */
+pub fn successive_halving(
+    base: &Settings,
+    search: &SearchSettings,
+    points: Vec<Vec<f64>>,
+    min_epochs: usize,
+    max_epochs: usize,
+    eta: usize,
+    cancel: &AtomicBool,
+) -> Result<Trials, String> {
+    if !(1..=MAX_TRIALS).contains(&points.len()) {
+        return Err(format!(
+            "Successive halving needs 1 to {} trials",
+            MAX_TRIALS
+        ));
+    }
+    let eta = validate_eta(eta)?;
+    let max_epochs = max_epochs.max(1);
+    let mut rungs = points
+        .into_iter()
+        .enumerate()
+        .map(|(index, point)| {
+            let values = search.values_at(&point);
+            let network = build_trial(base, &values);
+            Rung {
+                index,
+                values,
+                network,
+                pruned: false,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut epochs = min_epochs.clamp(1, max_epochs);
+    loop {
+        rungs
+            .par_iter_mut()
+            .filter(|rung| !rung.pruned)
+            .for_each(|rung| {
+                let trained = rung
+                    .network
+                    .as_mut()
+                    .map_or(Ok(()), |network| train(network, epochs, cancel));
+                if let Err(e) = trained {
+                    rung.network = Err(e);
+                }
+            });
+        if epochs >= max_epochs || cancel.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let mut alive = rungs
+            .iter_mut()
+            .filter(|rung| !rung.pruned && rung.network.is_ok())
+            .collect::<Vec<_>>();
+        if alive.len() <= 1 {
+            break;
+        }
+        alive.sort_by(|a, b| a.mse_validation().total_cmp(&b.mse_validation()));
+        let keep = (alive.len() / eta).max(1);
+        alive
+            .into_iter()
+            .skip(keep)
+            .for_each(|rung| rung.pruned = true);
+        epochs = epochs.saturating_mul(eta).min(max_epochs);
+    }
+
+    let leader = Leader::default();
+    let trials = rungs
+        .into_iter()
+        .map(|rung| {
+            TrialResult::new(
+                rung.index,
+                rung.values,
+                rung.network,
+                search.objective,
+                rung.pruned,
+                &leader,
+            )
+        })
+        .collect();
+    Ok((trials, leader))
+}
+
+/**
+ * Successive halving brackets with random trials, from many trials starting at one epoch to
+ * a few trained max_epochs from the start.
+ */
+pub fn hyperband(
+    base: &Settings,
+    search: &SearchSettings,
+    max_epochs: usize,
+    eta: usize,
+    cancel: &AtomicBool,
+) -> Result<Trials, String> {
+    let eta = validate_eta(eta)?;
+    let max_epochs = max_epochs.max(1);
+    // the largest s with eta^s <= max_epochs
+    let mut s_max = 0;
+    while eta
+        .checked_pow(s_max + 1)
+        .is_some_and(|epochs| epochs <= max_epochs)
+    {
+        s_max += 1;
+    }
+
+    let mut rng = search.rng();
+    let mut results = vec![];
+    let leader = Leader::default();
+    for s in (0..=s_max).rev() {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        let rate = eta.pow(s);
+        let trials = ((s_max as usize + 1) * rate).div_ceil(s as usize + 1);
+        let points = (0..trials).map(|_| search.sample(&mut rng)).collect();
+        let first = results.len();
+        let (bracket, bracket_leader) = successive_halving(
+            base,
+            search,
+            points,
+            max_epochs / rate,
+            max_epochs,
+            eta,
+            cancel,
+        )?;
+        results.extend(bracket.into_iter().map(|mut trial| {
+            trial.index += first;
+            trial
+        }));
+        leader.merge(bracket_leader, first);
+    }
+    Ok((results, leader))
+}
+
+impl Rung {
+    // diverged and failed networks are the worst
+    fn mse_validation(&self) -> f64 {
+        self.network
+            .as_ref()
+            .ok()
+            .filter(|network| !network.history.diverged)
+            .and_then(|network| network.history.mse_validation.last().copied())
+            .filter(|mse| mse.is_finite())
+            .unwrap_or(f64::INFINITY)
+    }
+}
+
+// the candidate of every parameter with the highest good / bad density ratio
+fn propose(search: &SearchSettings, observed: &[(Vec<f64>, f64)], rng: &mut StdRng) -> Vec<f64> {
+    let mut sorted = observed.iter().collect::<Vec<_>>();
+    sorted.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let good_count = ((GAMMA * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    let (good, bad) = sorted.split_at(good_count);
+
+    search
+        .parameters
+        .iter()
+        .enumerate()
+        .map(|(d, parameter)| {
+            let coordinates = |trials: &[&(Vec<f64>, f64)]| {
+                trials.iter().map(|(point, _)| point[d]).collect::<Vec<_>>()
+            };
+            let l = Parzen::new(&parameter.space, coordinates(good));
+            let g = Parzen::new(&parameter.space, coordinates(bad));
+            let ratio = |x: f64| l.density(x) / g.density(x);
+            (0..CANDIDATES)
+                .map(|_| l.sample(rng))
+                .max_by(|a, b| ratio(*a).total_cmp(&ratio(*b)))
+                .unwrap_or_else(|| rng.gen())
+        })
+        .collect()
+}
+
+fn validate_eta(eta: usize) -> Result<usize, String> {
+    match eta {
+        0 => Ok(DEFAULT_ETA),
+        1 => Err("eta must be at least 2".to_string()),
+        eta => Ok(eta),
+    }
+}
+
+// standard normal draw (Box-Muller)
+fn gaussian(rng: &mut StdRng) -> f64 {
+    let u = 1.0 - rng.gen::<f64>();
+    (-2.0 * u.ln()).sqrt() * (2.0 * PI * rng.gen::<f64>()).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{tests::settings, SearchParameter, SearchStrategy};
+    use rand::SeedableRng;
+
+    fn alpha_search() -> SearchSettings {
+        SearchSettings {
+            parameters: vec![SearchParameter {
+                path: "alpha".to_string(),
+                space: ParameterSpace::Range {
+                    min: 0.01,
+                    max: 0.5,
+                    steps: 0,
+                    log: false,
+                    integer: false,
+                },
+            }],
+            strategy: SearchStrategy::Hyperband { eta: 0 },
+            objective: None,
+            max_epochs: None,
+            seed: Some(3),
+        }
+    }
+
+    #[test]
+    fn proposals_follow_the_good_trials() {
+        let search = alpha_search();
+        // low coordinates had the lowest losses
+        let observed = (0..20)
+            .map(|i| {
+                let x = i as f64 / 19.0;
+                (vec![x], x)
+            })
+            .collect::<Vec<_>>();
+        let mut rng = StdRng::seed_from_u64(5);
+        let proposals = (0..20)
+            .map(|_| propose(&search, &observed, &mut rng)[0])
+            .collect::<Vec<_>>();
+        assert!(proposals.iter().sum::<f64>() / 20.0 < 0.3);
+    }
+
+    #[test]
+    fn bayesian_search_runs_every_trial() {
+        let search = alpha_search();
+        let (trials, leader) = tpe(&settings(), &search, 7, 2, 2, &AtomicBool::new(false)).unwrap();
+        // 2 initial trials, then batches of 4 and 1
+        assert_eq!(trials.len(), 7);
+        assert!(trials.iter().enumerate().all(|(i, t)| t.index == i));
+        assert!(leader.into_best().is_some());
+    }
+
+    #[test]
+    fn successive_halving_prunes_all_but_the_best() {
+        let search = alpha_search();
+        let mut rng = search.rng();
+        let points = (0..9).map(|_| search.sample(&mut rng)).collect();
+        let (trials, leader) = successive_halving(
+            &settings(),
+            &search,
+            points,
+            1,
+            9,
+            3,
+            &AtomicBool::new(false),
+        )
+        .unwrap();
+        let (pruned, complete): (Vec<_>, Vec<_>) = trials.iter().partition(|t| t.pruned);
+        assert_eq!(pruned.len(), 8);
+        assert_eq!(complete[0].epochs, 9);
+        assert_eq!(leader.into_best().unwrap().index, complete[0].index);
+        // 6 trials stopped after the first rung, 2 after the second
+        assert_eq!(pruned.iter().filter(|t| t.epochs == 1).count(), 6);
+        assert_eq!(pruned.iter().filter(|t| t.epochs == 3).count(), 2);
+
+        // brackets of 4, 3 and 3 trials
+        let (trials, _) = hyperband(&settings(), &search, 4, 2, &AtomicBool::new(false)).unwrap();
+        assert_eq!(trials.len(), 10);
+        assert!(trials.iter().enumerate().all(|(i, t)| t.index == i));
+    }
+}
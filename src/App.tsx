@@ -7,12 +7,13 @@ import "./App.css";
 import { Circle, FortyFiveDegRect, Rect, TestPointsPlot, Triangle } from "./testpoints";
 import { initialSettings, settingsReducer } from "./settings";
 import { initialState, State, stateReducer } from "./state";
-import { HeatMap } from "./heatmap";
+import { HeatMap, ValueLegend } from "./heatmap";
 
 import { loadPreset, PRESETS } from "./generate2D";
 import { ConfusionMatrixTable } from "./confusionMatrix";
 import { COLORS, SHAPES } from "./constants";
 import { LineChart } from "./linechart";
+import { SearchPanel } from "./search";
 
 
 function App() {
@@ -35,12 +36,22 @@ function App() {
       });
     });
 
+    let deltaUnlisten = listen<Partial<State>>("UPDATE_CLIENT_STATE_DELTA", (event) => {
+      startTransition(() => {
+        dispatchState({ type: "APPEND", payload: event.payload });
+      });
+    });
+
+    // the state may have changed before the listeners were registered
+    invoke<State>("resync_client_state").then(state => dispatchState({ type: "UPDATE", payload: state }));
+
     // let heatmapUnlestin = listen<number[][]>("HEATMAP", (event) => {
     //   startTransition(() => setHeatmapData(event.payload));
     // });
 
     return () => {
       epochUnlisten.then(f => f());
+      deltaUnlisten.then(f => f());
       // heatmapUnlestin.then(f => f());
     };
   }, [])
@@ -111,6 +122,13 @@ function App() {
     })
   }
 
+  async function searchHyperparameters(search: any) {
+    // the search trains on the settings of the active network
+    await invoke("reset", { settings: settings });
+    const result = await invoke("search_hyperparameters", { search });
+    dispatchState({ type: "SET_SEARCH", payload: { search: result } });
+  }
+
   return (
     <div className="flex justify-stretch items-stretch gap-2 h-screen overflow-hidden p-2 select-none">
       <div className="bg-gray-100 p-2 w-[300px] grid grid-cols-2 gap-2 content-start">
@@ -139,7 +157,7 @@ function App() {
       </div>
       <main className="h-screen overflow-auto w-full">
         <div className="bg-gray-100 p-2 w-full mb-2">
-          <div className="grid grid-cols-6 gap-2 items-end">
+          <div className="grid grid-cols-7 gap-2 items-end">
             <div className="flex flex-col items-stretch gap-2">
               <div className="text-center">Max Epochs</div>
               <input className="p-1" type="number" value={settings.maxEpochs} onChange={(e) => dispatchSettings({ type: "SET_MAX_EPOCHS", payload: { maxEpochs: +e.target.value } })} />
@@ -164,6 +182,10 @@ function App() {
                 <option value="0.2">0.2</option>
               </select>
             </div>
+            <div className="flex flex-col items-stretch gap-2">
+              <div className="text-center">Label Smoothing</div>
+              <input className="p-1" type="number" min={0} max={1} step={0.05} value={settings.labelSmoothing} onChange={(e) => dispatchSettings({ type: "SET_LABEL_SMOOTHING", payload: { labelSmoothing: +e.target.value } })} />
+            </div>
             <div className="flex flex-col items-stretch gap-2">
               <div className="text-center">Output Layer</div>
               <select className="p-1" value={settings.outputLayer.activationFunction} onChange={(e) => dispatchSettings({ type: "SET_OUTPUT_LAYER_ACTIVATION", payload: { activationFunction: e.target.value } })}>
@@ -172,7 +194,14 @@ function App() {
                 <option value="sigmoid">Sigmoid</option>
               </select>
             </div>
-            <div className="col-span-2 flex justify-end items-end gap-2">
+            <div className="flex flex-col items-stretch gap-2">
+              <div className="text-center">Precision</div>
+              <select className="p-1" value={settings.precision} onChange={(e) => dispatchSettings({ type: "SET_PRECISION", payload: { precision: e.target.value } })}>
+                <option value="f64">f64</option>
+                <option value="f32">f32</option>
+              </select>
+            </div>
+            <div className="flex justify-end items-end gap-2">
               <form
                 onSubmit={(e) => {
                   e.preventDefault();
@@ -191,6 +220,63 @@ function App() {
               </form>
             </div>
           </div>
+          <div className="grid grid-cols-6 gap-2 items-end mt-2">
+            <div className="flex flex-col items-stretch gap-2">
+              <div className="text-center">Heatmap Resolution</div>
+              <input className="p-1" type="number" min={2} max={1000} value={settings.heatmap.resolution} onChange={(e) => dispatchSettings({ type: "SET_HEATMAP", payload: { resolution: +e.target.value } })} />
+            </div>
+            <div className="flex flex-col items-stretch gap-2">
+              <div className="text-center">Heatmap Margin</div>
+              <input className="p-1" type="number" min={0} step={0.01} value={settings.heatmap.margin} onChange={(e) => dispatchSettings({ type: "SET_HEATMAP", payload: { margin: +e.target.value } })} />
+            </div>
+            <div className="flex flex-col items-stretch gap-2">
+              <div className="text-center">Update Every (Epochs)</div>
+              <input className="p-1" type="number" min={0} value={settings.heatmap.updateInterval} onChange={(e) => dispatchSettings({ type: "SET_HEATMAP", payload: { updateInterval: +e.target.value } })} />
+            </div>
+            <div className="flex flex-col items-center gap-2">
+              <div className="text-center">Fixed Bounds</div>
+              <input className="p-1 h-8" type="checkbox" checked={settings.heatmap.bounds !== null} onChange={(e) => dispatchSettings({ type: "SET_HEATMAP", payload: { bounds: e.target.checked ? (state.heatmapBounds ?? { x: [0, 100], y: [0, 100] }) : null } })} />
+            </div>
+            {(["x", "y"] as const).map(axis => (
+              <div key={axis} className="flex flex-col items-stretch gap-2">
+                <div className="text-center uppercase">{axis} Min / Max</div>
+                <div className="flex gap-1">
+                  {[0, 1].map(i => (
+                    <input key={i} className="p-1 w-1/2" type="number" disabled={settings.heatmap.bounds === null}
+                      value={settings.heatmap.bounds?.[axis][i] ?? ""}
+                      onChange={(e) => {
+                        const bounds = settings.heatmap.bounds!;
+                        const range: [number, number] = [...bounds[axis]];
+                        range[i] = +e.target.value;
+                        dispatchSettings({ type: "SET_HEATMAP", payload: { bounds: { ...bounds, [axis]: range } } });
+                      }} />
+                  ))}
+                </div>
+              </div>
+            ))}
+          </div>
+          <div className="grid grid-cols-6 gap-2 items-end mt-2">
+            <div className="flex flex-col items-stretch gap-2">
+              <div className="text-center">Task Type</div>
+              <select className="p-1" value={settings.taskType} onChange={(e) => dispatchSettings({ type: "SET_TASK_TYPE", payload: { taskType: e.target.value } })}>
+                <option value="classification">Classification</option>
+                <option value="multiLabel">Multi-Label</option>
+                <option value="regression">Regression</option>
+              </select>
+            </div>
+            {settings.taskType === "multiLabel" && (
+              <div className="col-span-5 flex flex-col items-stretch gap-2">
+                <div className="text-center">Label Thresholds</div>
+                <div className="flex gap-1">
+                  {Array.from({ length: settings.outputLayer.neuronsCount }, (_x, i) => (
+                    <input key={i} className="p-1 w-16" type="number" min={0} max={1} step={0.05} title={`Label ${i + 1}`}
+                      value={settings.labelThresholds[i] ?? 0.5}
+                      onChange={(e) => dispatchSettings({ type: "SET_LABEL_THRESHOLD", payload: { label: i, threshold: +e.target.value } })} />
+                  ))}
+                </div>
+              </div>
+            )}
+          </div>
         </div>
         <div className="bg-gray-100 p-2 w-full mb-2">
           <div className="flex justify-center items-center gap-2">
@@ -204,12 +290,28 @@ function App() {
             {settings.hiddenLayers.map((layer, index) => (
               <div key={index} className="flex flex-col items-stretch gap-2">
                 <div className="text-center">L{index + 1}</div>
-                <select className="p-1" value={layer.activationFunction} onChange={(e) => dispatchSettings({ type: "SET_LAYERS_ACTIVATION", payload: { layer: index, activationFunction: e.target.value } })}>
+                <select className="p-1" value={typeof layer.activationFunction === "string" ? layer.activationFunction : "custom"} onChange={(e) => dispatchSettings({ type: "SET_LAYERS_ACTIVATION", payload: { layer: index, activationFunction: e.target.value === "custom" ? { custom: "x / (1 + abs(x))" } : e.target.value } })}>
                   <option value="tanh">Tanh</option>
                   <option value="sigmoid">Sigmoid</option>
                   <option value="relu">Relu</option>
                   <option value="leakyRelu">Leaky Relu</option>
+                  <option value="prelu">PReLU</option>
+                  <option value="elu">ELU</option>
+                  <option value="selu">SELU</option>
+                  <option value="gelu">GELU</option>
+                  <option value="swish">Swish</option>
+                  <option value="mish">Mish</option>
+                  <option value="softplus">Softplus</option>
+                  <option value="softsign">Softsign</option>
+                  <option value="hardSigmoid">Hard Sigmoid</option>
+                  <option value="hardTanh">Hard Tanh</option>
+                  <option value="gaussian">Gaussian</option>
+                  <option value="custom">Custom</option>
                 </select>
+                {typeof layer.activationFunction !== "string" && (
+                  <input className="p-1" type="text" title="f(x)" value={layer.activationFunction.custom}
+                    onChange={(e) => dispatchSettings({ type: "SET_LAYERS_ACTIVATION", payload: { layer: index, activationFunction: { custom: e.target.value } } })} />
+                )}
                 <select className="p-1" value={layer.neuronsCount} onChange={(e) => dispatchSettings({ type: "SET_LAYERS_NEURONS", payload: { layer: index, neuronsCount: +e.target.value } })}>
                   {Array.from({ length: 129 }, (_x, i) => i).slice(1).map(i => (
                     <option key={i} value={i}>{`${i} Neuron`}</option>
@@ -242,7 +344,9 @@ function App() {
           <div className="width-[300px] bg-gray-100 p-2 mb-2">
             <div className="w-[300px] h-[300px] relative mb-2">
               {state.heatmap !== null && state.heatmap.length > 0 && (
-                <HeatMap data={state.heatmap} classesCount={settings.outputLayer.neuronsCount} />
+                <HeatMap data={state.heatmap} classesCount={settings.outputLayer.neuronsCount}
+                  resolution={state.heatmapResolution} bounds={state.heatmapBounds} taskType={settings.taskType}
+                  thresholds={settings.labelThresholds} />
               )}
               {settings.trainingData.length > 0 && (
                 <div className="absolute inset-0">
@@ -254,6 +358,11 @@ function App() {
                 </div>
               )}
             </div>
+            {state.heatmapRange !== null && (
+              <div className="mb-2">
+                <ValueLegend range={state.heatmapRange} />
+              </div>
+            )}
             <div className="flex justify-start items-start gap-2 w-[300px]">
               <div className="flex items-center gap-2">
                 <input id="testing_set_input" type="checkbox" checked={showTestingPoints} onChange={(e) => setShowTestingPoints(e.target.checked)}
@@ -307,6 +416,9 @@ function App() {
             )}
           </div>
         </div>
+        <div className="bg-gray-100 p-2 w-full mb-2">
+          <SearchPanel result={state.search} onSearch={searchHyperparameters} />
+        </div>
         {/* {state.parameters !== null && (
           <div className="bg-gray-100 p-2 w-full mb-2">
             <div className="flex flex-col items-stretch gap-2">
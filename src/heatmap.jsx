@@ -3,11 +3,17 @@ import * as d3 from 'd3';
 import { HEATMAP_COLORS } from "./constants";
 
 
-export function HeatMap({ data, classesCount }) {
+// the drawing area shows the inputs 0..100 on both axes
+const DOMAIN = 100;
+
+// colors of the scaled 0..1 values of a regression heatmap
+const valueColor = d3.scaleSequential(d3.interpolateViridis).domain([0, 1]);
+
+export function HeatMap({ data, classesCount, resolution, bounds, taskType, thresholds }) {
 
     const
-        n = 50,
-        m = 50,
+        n = resolution ?? 50,
+        m = resolution ?? 50,
         width = 300,
         height = 300;
 
@@ -58,11 +64,54 @@ export function HeatMap({ data, classesCount }) {
             return;
         }
 
-        const path = d3.geoPath().projection(d3.geoIdentity().scale(width / n));
+        // grid cells to pixels, the cells span the bounds of the heatmap
+        const { x, y } = bounds ?? { x: [0, DOMAIN], y: [0, DOMAIN] };
+        const kx = (x[1] - x[0]) / n * width / DOMAIN,
+            ky = (y[1] - y[0]) / m * height / DOMAIN;
+        const projection = d3.geoTransform({
+            point: function (i, j) {
+                this.stream.point(x[0] * width / DOMAIN + i * kx, y[0] * height / DOMAIN + j * ky);
+            }
+        });
+        const path = d3.geoPath().projection(projection);
         const contours = d3.contours().size([n, m]);
 
         svg.selectAll('*').remove();
 
+        // one surface of the (first) predicted value
+        if (taskType === "regression") {
+            svg.append("g")
+                .selectAll()
+                .data(d3.range(0, 1 + 1E-9, 0.05))
+                .join("path")
+                .attr("d", d => path(contours.contour(pathData(0), d)))
+                .attr("fill", valueColor)
+                .attr("fill-opacity", 0.6);
+            return;
+        }
+
+        // independent labels: every probability shaded on its own, outlined at its threshold
+        if (taskType === "multiLabel") {
+            for (let i = 0; i < classesCount; i++) {
+                const probability = d3.scaleLinear()
+                    .domain([0, 1])
+                    .range(["transparent", HEATMAP_COLORS[i]]);
+                svg.append("g")
+                    .selectAll()
+                    .data(d3.range(0.1, 1, 0.1))
+                    .join("path")
+                    .attr("d", d => path(contours.contour(pathData(i), d)))
+                    .attr("fill", probability)
+                    .attr("fill-opacity", 0.3);
+                svg.append("path")
+                    .attr("d", path(contours.contour(pathData(i), thresholds?.[i] ?? 0.5)))
+                    .attr("fill", "none")
+                    .attr("stroke", HEATMAP_COLORS[i])
+                    .attr("stroke-width", 2);
+            }
+            return;
+        }
+
         for (let i = 0; i < classesCount; i++) {
             svg.append("g")
                 .selectAll()
@@ -72,7 +121,7 @@ export function HeatMap({ data, classesCount }) {
                 .attr("fill", pathColor(i));
         }
 
-    }, [svg, data]);
+    }, [svg, data, resolution, bounds, taskType, thresholds]);
 
 
     return (
@@ -80,4 +129,16 @@ export function HeatMap({ data, classesCount }) {
             <svg ref={ref} />
         </div>
     );
-}
\ No newline at end of file
+}
+
+// the colors of a regression heatmap and the predicted values they stand for
+export function ValueLegend({ range }) {
+    const stops = d3.range(0, 1 + 1E-9, 0.1).map(v => valueColor(v)).join(", ");
+    return (
+        <div className="flex items-center gap-2 w-[300px]">
+            <span>{range[0].toPrecision(3)}</span>
+            <div className="h-3 grow opacity-60" style={{ background: `linear-gradient(to right, ${stops})` }} />
+            <span>{range[1].toPrecision(3)}</span>
+        </div>
+    );
+}
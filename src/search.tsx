@@ -0,0 +1,194 @@
+import { useState } from "react";
+
+import { SearchResult } from "./state";
+
+type Space =
+    | { type: "range", min: number, max: number, steps: number, log: boolean, integer: boolean }
+    | { type: "choice", values: any[] };
+
+interface Parameter {
+    path: string;
+    space: Space;
+}
+
+type Strategy = "grid" | "random" | "bayesian" | "successiveHalving" | "hyperband";
+
+const OBJECTIVES = [
+    { value: "", label: "Validation MSE" },
+    { value: "validationAccuracy", label: "Validation Accuracy" },
+    { value: "validationCrossEntropy", label: "Validation Cross Entropy" },
+    { value: "validationF1", label: "Validation F1" },
+];
+
+const INITIAL_PARAMETERS: Parameter[] = [
+    { path: "alpha", space: { type: "range", min: 0.001, max: 0.2, steps: 4, log: true, integer: false } },
+    { path: "hiddenLayers.0.neuronsCount", space: { type: "choice", values: [4, 8, 16] } },
+];
+
+// the strategy in the shape search_hyperparameters expects
+function strategyJson(strategy: Strategy, trials: number, minEpochs: number) {
+    switch (strategy) {
+        case "grid": return { type: "grid" };
+        case "random": return { type: "random", trials };
+        case "bayesian": return { type: "bayesian", trials };
+        case "successiveHalving": return { type: "successiveHalving", trials, minEpochs };
+        case "hyperband": return { type: "hyperband" };
+    }
+}
+
+export function SearchPanel({ result, onSearch }: {
+    result: SearchResult | null,
+    onSearch: (search: any) => Promise<void>,
+}) {
+    const [parameters, setParameters] = useState<Parameter[]>(INITIAL_PARAMETERS);
+    const [strategy, setStrategy] = useState<Strategy>("random");
+    const [trials, setTrials] = useState(10);
+    const [minEpochs, setMinEpochs] = useState(10);
+    const [maxEpochs, setMaxEpochs] = useState(100);
+    const [objective, setObjective] = useState("");
+    const [isSearching, setIsSearching] = useState(false);
+    const [error, setError] = useState<string | null>(null);
+
+    function setParameter(index: number, parameter: Parameter) {
+        setParameters(parameters.map((p, i) => i === index ? parameter : p));
+    }
+
+    async function search() {
+        setIsSearching(true);
+        setError(null);
+        try {
+            await onSearch({
+                parameters,
+                strategy: strategyJson(strategy, trials, minEpochs),
+                objective: objective || null,
+                maxEpochs,
+                seed: null,
+            });
+        } catch (e) {
+            setError(String(e));
+        } finally {
+            setIsSearching(false);
+        }
+    }
+
+    const paths = parameters.map(p => p.path);
+
+    return (
+        <div className="flex flex-col items-stretch gap-2">
+            <div className="text-center">HYPERPARAMETER SEARCH</div>
+            {parameters.map((parameter, index) => (
+                <div key={index} className="flex items-end gap-2">
+                    <input className="p-1 w-64" type="text" title="Setting path" value={parameter.path}
+                        onChange={(e) => setParameter(index, { ...parameter, path: e.target.value })} />
+                    <select className="p-1" value={parameter.space.type} onChange={(e) => setParameter(index, {
+                        ...parameter,
+                        space: e.target.value === "range"
+                            ? { type: "range", min: 0, max: 1, steps: 3, log: false, integer: false }
+                            : { type: "choice", values: [] },
+                    })}>
+                        <option value="range">Range</option>
+                        <option value="choice">Choice</option>
+                    </select>
+                    {parameter.space.type === "range" ? (() => {
+                        const space = parameter.space;
+                        return <>
+                            <input className="p-1 w-24" type="number" title="Min" value={space.min}
+                                onChange={(e) => setParameter(index, { ...parameter, space: { ...space, min: +e.target.value } })} />
+                            <input className="p-1 w-24" type="number" title="Max" value={space.max}
+                                onChange={(e) => setParameter(index, { ...parameter, space: { ...space, max: +e.target.value } })} />
+                            <input className="p-1 w-16" type="number" title="Grid steps" min={0} value={space.steps}
+                                onChange={(e) => setParameter(index, { ...parameter, space: { ...space, steps: +e.target.value } })} />
+                            <label className="flex items-center gap-1">
+                                <input type="checkbox" checked={space.log}
+                                    onChange={(e) => setParameter(index, { ...parameter, space: { ...space, log: e.target.checked } })} />
+                                Log
+                            </label>
+                            <label className="flex items-center gap-1">
+                                <input type="checkbox" checked={space.integer}
+                                    onChange={(e) => setParameter(index, { ...parameter, space: { ...space, integer: e.target.checked } })} />
+                                Integer
+                            </label>
+                        </>;
+                    })() : (
+                        // comma separated JSON values, e.g. 4, 8, 16
+                        <input className="p-1 w-64" type="text" title="Values" defaultValue={JSON.stringify(parameter.space.values).slice(1, -1)}
+                            onBlur={(e) => {
+                                try {
+                                    setParameter(index, { ...parameter, space: { type: "choice", values: JSON.parse(`[${e.target.value}]`) } });
+                                } catch {
+                                    setError(`Invalid values for ${parameter.path}`);
+                                }
+                            }} />
+                    )}
+                    <button type="button" className="h-6 w-6 rounded-full bg-gray-300 hover:bg-gray-400"
+                        onClick={() => setParameters(parameters.filter((_, i) => i !== index))}>-</button>
+                </div>
+            ))}
+            <div className="grid grid-cols-6 gap-2 items-end">
+                <button type="button" className="py-1 px-2.5 bg-gray-300 hover:bg-gray-400"
+                    onClick={() => setParameters([...parameters, { path: "", space: { type: "range", min: 0, max: 1, steps: 3, log: false, integer: false } }])}>+ Parameter</button>
+                <div className="flex flex-col items-stretch gap-2">
+                    <div className="text-center">Strategy</div>
+                    <select className="p-1" value={strategy} onChange={(e) => setStrategy(e.target.value as Strategy)}>
+                        <option value="grid">Grid</option>
+                        <option value="random">Random</option>
+                        <option value="bayesian">Bayesian</option>
+                        <option value="successiveHalving">Successive Halving</option>
+                        <option value="hyperband">Hyperband</option>
+                    </select>
+                </div>
+                <div className="flex flex-col items-stretch gap-2">
+                    <div className="text-center">Trials</div>
+                    <input className="p-1" type="number" min={1} value={trials} disabled={strategy === "grid" || strategy === "hyperband"}
+                        onChange={(e) => setTrials(+e.target.value)} />
+                </div>
+                <div className="flex flex-col items-stretch gap-2">
+                    <div className="text-center">Min / Max Epochs</div>
+                    <div className="flex gap-1">
+                        <input className="p-1 w-1/2" type="number" min={1} value={minEpochs} disabled={strategy !== "successiveHalving"}
+                            onChange={(e) => setMinEpochs(+e.target.value)} />
+                        <input className="p-1 w-1/2" type="number" min={1} value={maxEpochs}
+                            onChange={(e) => setMaxEpochs(+e.target.value)} />
+                    </div>
+                </div>
+                <div className="flex flex-col items-stretch gap-2">
+                    <div className="text-center">Objective</div>
+                    <select className="p-1" value={objective} onChange={(e) => setObjective(e.target.value)}>
+                        {OBJECTIVES.map(o => <option key={o.value} value={o.value}>{o.label}</option>)}
+                    </select>
+                </div>
+                <button type="button" disabled={isSearching} className="py-1 px-2.5 text-white bg-gray-600 hover:bg-gray-700 uppercase"
+                    onClick={search}>{isSearching ? "Searching..." : "Search"}</button>
+            </div>
+            {error !== null && <div className="text-center text-red-700">{error}</div>}
+            {result !== null && (
+                <table className="table-auto w-full">
+                    <thead>
+                        <tr>
+                            <th className="border border-gray-400 bg-gray-200">#</th>
+                            {paths.map(path => <th key={path} className="border border-gray-400 bg-gray-200">{path}</th>)}
+                            <th className="border border-gray-400 bg-gray-200">{OBJECTIVES.find(o => o.value === (result.objective ?? ""))?.label ?? result.objective}</th>
+                            <th className="border border-gray-400 bg-gray-200">Epochs</th>
+                            <th className="border border-gray-400 bg-gray-200">MSE (Validation)</th>
+                            <th className="border border-gray-400 bg-gray-200">Status</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {result.trials.map(trial => (
+                            <tr key={trial.index} className={trial.index === result.best?.index ? "font-bold" : ""}>
+                                <td className="border border-gray-400 text-center">{trial.index + 1}</td>
+                                {paths.map(path => <td key={path} className="border border-gray-400 text-center">{JSON.stringify(trial.values[path] ?? "-")}</td>)}
+                                <td className="border border-gray-400 text-center">{trial.score === null ? '-' : trial.score.toPrecision(5)}</td>
+                                <td className="border border-gray-400 text-center">{trial.epochs}</td>
+                                <td className="border border-gray-400 text-center">{trial.mseValidation === null ? '-' : trial.mseValidation.toPrecision(5)}</td>
+                                <td className="border border-gray-400 text-center">
+                                    {trial.error ?? (trial.diverged ? "Diverged" : trial.pruned ? "Pruned" : "Complete")}
+                                </td>
+                            </tr>
+                        ))}
+                    </tbody>
+                </table>
+            )}
+        </div>
+    );
+}
@@ -1,16 +1,40 @@
 import { preset } from "./generate2D";
 
+export interface HeatmapBounds {
+    x: [number, number];
+    y: [number, number];
+}
+
+export interface HeatmapSettings {
+    // cells along each axis
+    resolution: number;
+    // fixed bounds, fitted to the data extent when null
+    bounds: HeatmapBounds | null;
+    // fraction of the data extent added on every side of fitted bounds
+    margin: number;
+    // epochs between two heatmap updates while learning, 0 only updates when learning ends
+    updateInterval: number;
+}
+
+// classification picks one class per point, multi-label any number, regression fits values
+export type TaskType = "classification" | "multiLabel" | "regression";
+
+// a named activation function or a math expression of x, e.g. { custom: "x / (1 + abs(x))" }
+export type ActivationFunction = string | { custom: string };
+
 export interface Settings {
     layersCount: number;
     hiddenLayers: {
         neuronsCount: number;
-        activationFunction: string;
+        activationFunction: ActivationFunction;
     }[];
     outputLayer: {
         neuronsCount: number;
         activationFunction: string;
     }
     alpha: number;
+    // ε mixing the training targets with the uniform distribution, 0 trains on the raw targets
+    labelSmoothing: number;
     maxEpochs: number;
     desiredMse: number;
     // validationRatio: number;
@@ -22,6 +46,12 @@ export interface Settings {
     density: number;
     preset: number | null;
     scale: [number, number];
+    // f32 trains faster and uses half the memory
+    precision: "f32" | "f64";
+    heatmap: HeatmapSettings;
+    taskType: TaskType;
+    // per label decision thresholds in multi-label mode, 0.5 when missing
+    labelThresholds: number[];
 }
 
 export const initialSettings: Settings = {
@@ -32,6 +62,7 @@ export const initialSettings: Settings = {
     ],
     outputLayer: { neuronsCount: 2, activationFunction: "softmax" },
     alpha: 0.03,
+    labelSmoothing: 0,
     maxEpochs: 500,
     desiredMse: 0.01,
     trainingData: preset(9, 56, 0), // 70%
@@ -41,6 +72,10 @@ export const initialSettings: Settings = {
     density: 80,
     preset: 9,
     scale: [3, 3],
+    precision: "f64",
+    heatmap: { resolution: 50, bounds: null, margin: 0.05, updateInterval: 1 },
+    taskType: "classification",
+    labelThresholds: [],
 }
 
 export function settingsReducer(state: Settings, action: { type: string, payload?: any }): Settings {
@@ -64,6 +99,8 @@ export function settingsReducer(state: Settings, action: { type: string, payload
         return { ...state, hiddenLayers: layers };
     } else if (action.type === 'SET_ALPHA') {
         return { ...state, alpha: action.payload.alpha };
+    } else if (action.type === 'SET_LABEL_SMOOTHING') {
+        return { ...state, labelSmoothing: action.payload.labelSmoothing };
     } else if (action.type === 'SET_MAX_EPOCHS') {
         return { ...state, maxEpochs: action.payload.maxEpochs };
     } else if (action.type === 'SET_DESIRED_MSE') {
@@ -86,6 +123,16 @@ export function settingsReducer(state: Settings, action: { type: string, payload
         return { ...state, preset: action.payload.preset };
     } else if (action.type === 'SET_SCALE') {
         return { ...state, scale: action.payload.scale };
+    } else if (action.type === 'SET_HEATMAP') {
+        return { ...state, heatmap: { ...state.heatmap, ...action.payload } };
+    } else if (action.type === 'SET_PRECISION') {
+        return { ...state, precision: action.payload.precision };
+    } else if (action.type === 'SET_TASK_TYPE') {
+        return { ...state, taskType: action.payload.taskType };
+    } else if (action.type === 'SET_LABEL_THRESHOLD') {
+        let thresholds = Array.from({ length: state.outputLayer.neuronsCount }, (_x, i) => state.labelThresholds[i] ?? 0.5);
+        thresholds[action.payload.label] = action.payload.threshold;
+        return { ...state, labelThresholds: thresholds };
     } else {
         throw Error('Unknown action.');
     }
@@ -1,4 +1,22 @@
 
+export interface SearchTrial {
+    index: number;
+    values: Record<string, any>;
+    score: number | null;
+    epochs: number;
+    mseValidation: number | null;
+    diverged: boolean;
+    pruned: boolean;
+    error: string | null;
+}
+
+export interface SearchResult {
+    objective: string | null;
+    // best first, failed trials last
+    trials: SearchTrial[];
+    best: { index: number, settings: any, model: any } | null;
+}
+
 export interface State {
     mse: number | null;
     mseValidation: number | null;
@@ -8,6 +26,13 @@ export interface State {
     confusionMatrix: number[][] | null;
     crossEntropyLoss: number | null;
     predicted: number[] | null;
+    attributions: {
+        method: 'integratedGradients' | 'kernelShap',
+        baseline: number[],
+        baselineOutputs: number[],
+        outputs: number[],
+        values: number[][],
+    } | null;
     isLearning: boolean;
     parameters: [
         number[],
@@ -15,6 +40,36 @@ export interface State {
         string
     ][][] | null;
     heatmap: number[][] | null;
+    heatmapResolution: number | null;
+    heatmapBounds: { x: [number, number], y: [number, number] } | null;
+    // (min, max) predicted value of a regression heatmap, its cells are scaled to 0..1
+    heatmapRange: [number, number] | null;
+    heatmapSurfaces: {
+        predictedClass: number[],
+        confidence: number[],
+        entropy: number[],
+        margin: number[],
+    } | null;
+    metricHistory: Record<string, number[]> | null;
+    layerStats: {
+        layers: {
+            weightMean: number,
+            weightStd: number,
+            weightHistogram: { min: number, max: number, counts: number[] },
+            gradientNorm: number,
+            deadUnits: number | null,
+            saturation: number | null,
+        }[],
+        warnings: { layer: number, kind: string, value: number, message: string }[],
+    } | null;
+    neuronHeatmaps: {
+        resolution: number,
+        bounds: { x: [number, number], y: [number, number] },
+        layers: number[][][],
+    } | null;
+    runId: string | null;
+    // last hyperparameter search, kept by the frontend only
+    search: SearchResult | null;
 }
 
 export const initialState: State = {
@@ -26,9 +81,19 @@ export const initialState: State = {
     confusionMatrix: null,
     crossEntropyLoss: null,
     predicted: null,
+    attributions: null,
     isLearning: false,
     parameters: null,
     heatmap: null,
+    heatmapResolution: null,
+    heatmapBounds: null,
+    heatmapRange: null,
+    heatmapSurfaces: null,
+    metricHistory: null,
+    layerStats: null,
+    neuronHeatmaps: null,
+    runId: null,
+    search: null,
 };
 
 export function stateReducer(state: State, action: { type: string, payload?: any }): State {
@@ -36,6 +101,21 @@ export function stateReducer(state: State, action: { type: string, payload?: any
         console.debug('UPDATE', action.payload);
         return { ...state, ...action.payload };
     }
+    else if (action.type === 'APPEND') {
+        // delta update: histories only carry the points since the previous update
+        const { mseHistory, mseValidationHistory, metricHistory, ...rest } = action.payload;
+        const mergedMetricHistory: Record<string, number[]> = { ...(state.metricHistory || {}) };
+        for (const [metric, values] of Object.entries<number[]>(metricHistory)) {
+            mergedMetricHistory[metric] = [...(mergedMetricHistory[metric] || []), ...values];
+        }
+        return {
+            ...state,
+            ...rest,
+            mseHistory: [...(state.mseHistory || []), ...mseHistory],
+            mseValidationHistory: [...(state.mseValidationHistory || []), ...mseValidationHistory],
+            metricHistory: mergedMetricHistory,
+        };
+    }
     else if (action.type === 'OVERRIDE') {
         return action.payload;
     } else if (action.type === 'SET_MSE') {
@@ -68,6 +148,8 @@ export function stateReducer(state: State, action: { type: string, payload?: any
         return { ...state, isLearning: action.payload.isLearning };
     } else if (action.type === 'SET_PARAMETERS') {
         return { ...state, parameters: action.payload.parameters };
+    } else if (action.type === 'SET_SEARCH') {
+        return { ...state, search: action.payload.search };
     }
     throw Error('Unknown action.');
 }